@@ -0,0 +1,145 @@
+//! Throughput of the async checker against the thread-per-worker design it
+//! replaced, measured against a local server that answers every request
+//! after a fixed delay, like a slow but healthy site.
+//!
+//! Run with `cargo bench`. `BENCH_URLS` (default 2000) and `BENCH_DELAY_MS`
+//! (default 50) change the workload.
+
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use website_status_checker::Checker;
+
+fn main() {
+    let urls: usize = env_or("BENCH_URLS", 2000);
+    let delay = Duration::from_millis(env_or("BENCH_DELAY_MS", 50));
+    let addr = start_server(delay);
+    let targets: Vec<String> = (0..urls)
+        .map(|i| format!("http://{}/site/{}", addr, i))
+        .collect();
+
+    println!(
+        "{} URLs against a local server answering after {} ms",
+        urls,
+        delay.as_millis()
+    );
+    println!("{:<36} {:>10} {:>12}", "engine", "seconds", "checks/s");
+
+    // The same concurrency levels for both engines: the blocking design
+    // needs an OS thread (plus its client's runtime thread) per request in
+    // flight, the async one a task
+    for concurrency in [64, 256, 1024] {
+        report(&format!("blocking ({} threads)", concurrency), urls, || {
+            blocking_pool(&targets, concurrency)
+        });
+        let checker = Checker::builder()
+            .workers(concurrency)
+            .timeout(Duration::from_secs(30))
+            .build();
+        report(&format!("async ({} in flight)", concurrency), urls, || {
+            checker
+                .run(&targets)
+                .iter()
+                .filter(|s| s.is_success())
+                .count()
+        });
+    }
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Times `run`, which returns how many checks succeeded, and prints a row.
+fn report(engine: &str, urls: usize, run: impl FnOnce() -> usize) {
+    let start = Instant::now();
+    let succeeded = run();
+    let elapsed = start.elapsed().as_secs_f64();
+    assert_eq!(succeeded, urls, "{}: some checks failed", engine);
+    println!(
+        "{:<36} {:>10.2} {:>12.0}",
+        engine,
+        elapsed,
+        urls as f64 / elapsed
+    );
+}
+
+/// The previous design: `workers` threads, each with a blocking client,
+/// sharing one job queue behind a mutex.
+fn blocking_pool(targets: &[String], workers: usize) -> usize {
+    let (tx, rx) = mpsc::channel::<String>();
+    let rx = Arc::new(Mutex::new(rx));
+    for url in targets {
+        tx.send(url.clone()).unwrap();
+    }
+    drop(tx);
+
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let rx = Arc::clone(&rx);
+            thread::spawn(move || {
+                let client = reqwest::blocking::Client::new();
+                let mut succeeded = 0;
+                loop {
+                    let url = match rx.lock().unwrap().recv() {
+                        Ok(url) => url,
+                        Err(_) => break,
+                    };
+                    if client
+                        .get(&url)
+                        .send()
+                        .is_ok_and(|r| r.status().is_success())
+                    {
+                        succeeded += 1;
+                    }
+                }
+                succeeded
+            })
+        })
+        .collect();
+    handles.into_iter().map(|h| h.join().unwrap()).sum()
+}
+
+/// Starts a keep-alive HTTP server that waits `delay` before answering each
+/// request with `200 OK`, and returns its address.
+fn start_server(delay: Duration) -> std::net::SocketAddr {
+    let (addr_tx, addr_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async move {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            addr_tx.send(listener.local_addr().unwrap()).unwrap();
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let mut request = Vec::new();
+                    loop {
+                        let Ok(n @ 1..) = stream.read(&mut buf).await else {
+                            return;
+                        };
+                        request.extend_from_slice(&buf[..n]);
+                        // Answer each complete request head; bodies are never sent
+                        while let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                            request.drain(..end + 4);
+                            tokio::time::sleep(delay).await;
+                            let response = b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+                            if stream.write_all(response).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+    });
+    addr_rx.recv().unwrap()
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_TYPE, RETRY_AFTER};
+use serde::Serialize;
+
+use crate::retry::RetryPolicy;
+use crate::status::WebsiteStatus;
+
+/// Which way a target flipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertEvent {
+    /// The target failed enough consecutive checks to be considered down.
+    Down,
+    /// A target that was down passed a check again.
+    Recovered,
+}
+
+/// A state change of one target, sent as the webhook payload.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    pub event: AlertEvent,
+    pub url: String,
+    pub name: Option<String>,
+    /// Failed checks in a row, including this one for `down`, or the length
+    /// of the outage for `recovered`.
+    pub consecutive_failures: u32,
+    /// The check that caused the change.
+    pub status: WebsiteStatus,
+}
+
+/// Tracks whether each target is up or down, keyed by URL, and reports only
+/// transitions.
+///
+/// A target starts out up. It goes down after `failures_before_alert`
+/// consecutive failed checks, and comes back up on its next successful one.
+#[derive(Debug, Clone)]
+pub struct AlertTracker {
+    failures_before_alert: u32,
+    targets: HashMap<String, TargetState>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TargetState {
+    consecutive_failures: u32,
+    down: bool,
+}
+
+impl AlertTracker {
+    /// Values below one are treated as one.
+    pub fn new(failures_before_alert: u32) -> Self {
+        AlertTracker {
+            failures_before_alert: failures_before_alert.max(1),
+            targets: HashMap::new(),
+        }
+    }
+
+    pub fn failures_before_alert(&self) -> u32 {
+        self.failures_before_alert
+    }
+
+    /// Feeds in a check result, returning an alert if the target changed
+    /// state.
+    pub fn observe(&mut self, status: &WebsiteStatus) -> Option<Alert> {
+        let state = self.targets.entry(status.url().to_string()).or_default();
+
+        let event = if status.is_success() {
+            let recovered = state.down;
+            let failures = state.consecutive_failures;
+            *state = TargetState::default();
+            recovered.then_some((AlertEvent::Recovered, failures))
+        } else {
+            state.consecutive_failures += 1;
+            if !state.down && state.consecutive_failures >= self.failures_before_alert {
+                state.down = true;
+                Some((AlertEvent::Down, state.consecutive_failures))
+            } else {
+                None
+            }
+        };
+
+        event.map(|(event, consecutive_failures)| Alert {
+            event,
+            url: status.url().to_string(),
+            name: status.name().map(str::to_string),
+            consecutive_failures,
+            status: status.clone(),
+        })
+    }
+}
+
+/// Delivers alerts by POSTing them as JSON to an HTTP endpoint.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    url: String,
+    client: Client,
+    timeout: Duration,
+    retry: RetryPolicy,
+}
+
+impl Webhook {
+    /// A webhook with a 10 second timeout that retries transport errors,
+    /// `429` and `5xx` responses three times.
+    pub fn new(url: impl Into<String>) -> Self {
+        Webhook {
+            url: url.into(),
+            client: Client::new(),
+            timeout: Duration::from_secs(10),
+            retry: RetryPolicy::new(3)
+                .with_base_delay(Duration::from_millis(500))
+                .with_retry_on((500..600).chain([429])),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How many times a failed delivery is retried, keeping the default
+    /// delays and retryable status codes.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retry = self.retry.with_retries(retries);
+        self
+    }
+
+    /// When failed deliveries are retried; the policy's retryable status
+    /// codes decide which error responses are worth another attempt.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Sends `alert`, blocking until the endpoint accepts it with a `2xx`
+    /// response or the retries run out.
+    pub fn send(&self, alert: &Alert) -> Result<(), String> {
+        let payload = serde_json::to_vec(alert).map_err(|err| err.to_string())?;
+        let mut attempts = 0;
+        loop {
+            let response = self
+                .client
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(payload.clone())
+                .timeout(self.timeout)
+                .send();
+            attempts += 1;
+
+            let (error, retryable, retry_after) = match response {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let code = response.status().as_u16();
+                    let retry_after = response
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .map(str::to_string);
+                    (
+                        format!("HTTP {}", code),
+                        self.retry.should_retry_status(code),
+                        retry_after,
+                    )
+                }
+                Err(err) => (err.to_string(), true, None),
+            };
+
+            if !retryable || attempts > self.retry.retries() {
+                return Err(format!(
+                    "webhook {} failed after {} attempt{}: {}",
+                    self.url,
+                    attempts,
+                    if attempts == 1 { "" } else { "s" },
+                    error
+                ));
+            }
+            thread::sleep(self.retry.delay(attempts, retry_after.as_deref()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::Failure;
+    use crate::target::Target;
+    use chrono::Local;
+    use std::time::Instant;
+
+    fn status(up: bool) -> WebsiteStatus {
+        let failure = (!up).then(|| Failure::Request("connection refused".to_string()));
+        WebsiteStatus::new(
+            &Target::new("https://example.com").with_name("Example"),
+            up.then_some(200),
+            failure,
+            Duration::from_millis(12),
+            Local::now(),
+        )
+    }
+
+    #[test]
+    fn test_alerts_only_on_transitions() {
+        let mut tracker = AlertTracker::new(2);
+        let events: Vec<_> = [true, false, false, false, true, true, false]
+            .into_iter()
+            .map(|up| {
+                tracker
+                    .observe(&status(up))
+                    .map(|a| (a.event, a.consecutive_failures))
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                None,
+                None,
+                Some((AlertEvent::Down, 2)),
+                None,
+                Some((AlertEvent::Recovered, 3)),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_targets_are_tracked_separately() {
+        let mut tracker = AlertTracker::new(1);
+        assert!(tracker.observe(&status(false)).is_some());
+        let other = WebsiteStatus::new(
+            &Target::new("https://other.example"),
+            Some(200),
+            None,
+            Duration::ZERO,
+            Local::now(),
+        );
+        assert!(tracker.observe(&other).is_none());
+        assert!(tracker.observe(&status(false)).is_none());
+    }
+
+    #[test]
+    fn test_webhook_posts_json_and_retries() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+        let receiver = thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for code in [503, 200] {
+                let mut request = server.recv().unwrap();
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                bodies.push(body);
+                request.respond(tiny_http::Response::empty(code)).unwrap();
+            }
+            bodies
+        });
+
+        let webhook = Webhook::new(&url)
+            .with_retry_policy(RetryPolicy::new(2).with_base_delay(Duration::from_millis(10)));
+        let alert = AlertTracker::new(1).observe(&status(false)).unwrap();
+        webhook.send(&alert).unwrap();
+
+        let bodies = receiver.join().unwrap();
+        assert_eq!(bodies.len(), 2);
+        let payload: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+        assert_eq!(payload["event"], "down");
+        assert_eq!(payload["url"], "https://example.com");
+        assert_eq!(payload["name"], "Example");
+        assert_eq!(payload["consecutive_failures"], 1);
+        assert_eq!(payload["status"]["failure"]["kind"], "request");
+    }
+
+    #[test]
+    fn test_webhook_gives_up_on_client_errors() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+        let receiver = thread::spawn(move || {
+            let request = server.recv().unwrap();
+            request.respond(tiny_http::Response::empty(404)).unwrap();
+        });
+
+        let alert = AlertTracker::new(1).observe(&status(false)).unwrap();
+        let err = Webhook::new(&url).send(&alert).unwrap_err();
+        receiver.join().unwrap();
+        assert!(err.ends_with("failed after 1 attempt: HTTP 404"), "{}", err);
+    }
+
+    #[test]
+    fn test_webhook_waits_for_retry_after() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+        let receiver = thread::spawn(move || {
+            let mut received = Vec::new();
+            for _ in 0..2 {
+                let request = server.recv().unwrap();
+                received.push(Instant::now());
+                let retry_after = "Retry-After: 1".parse::<tiny_http::Header>().unwrap();
+                let response = tiny_http::Response::empty(429).with_header(retry_after);
+                request.respond(response).unwrap();
+            }
+            received
+        });
+
+        let alert = AlertTracker::new(1).observe(&status(false)).unwrap();
+        let err = Webhook::new(&url).with_retries(1).send(&alert).unwrap_err();
+        let received = receiver.join().unwrap();
+        assert!(
+            err.ends_with("failed after 2 attempts: HTTP 429"),
+            "{}",
+            err
+        );
+        assert!(received[1] - received[0] >= Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+
+use regex::Regex;
+
+/// A condition a response body must satisfy for a check to succeed.
+#[derive(Debug, Clone)]
+pub enum BodyAssertion {
+    /// The body must contain the given text.
+    Contains(String),
+    /// The body must not contain the given text.
+    NotContains(String),
+    /// The body must match the given regular expression.
+    Matches(Regex),
+}
+
+impl BodyAssertion {
+    /// Builds a [`BodyAssertion::Matches`], failing if `pattern` is not a
+    /// valid regular expression.
+    pub fn matches(pattern: &str) -> Result<Self, String> {
+        Regex::new(pattern)
+            .map(BodyAssertion::Matches)
+            .map_err(|err| format!("invalid regex '{}': {}", pattern, err))
+    }
+
+    /// Checks `body`, returning a description of the failure if it does not
+    /// satisfy the assertion.
+    pub fn check(&self, body: &str) -> Result<(), String> {
+        let passed = match self {
+            BodyAssertion::Contains(text) => body.contains(text.as_str()),
+            BodyAssertion::NotContains(text) => !body.contains(text.as_str()),
+            BodyAssertion::Matches(regex) => regex.is_match(body),
+        };
+        if passed {
+            Ok(())
+        } else {
+            Err(format!("body {}", self.negated()))
+        }
+    }
+
+    /// Describes the opposite of this assertion, as observed on failure.
+    fn negated(&self) -> String {
+        match self {
+            BodyAssertion::Contains(text) => format!("does not contain {:?}", text),
+            BodyAssertion::NotContains(text) => format!("contains {:?}", text),
+            BodyAssertion::Matches(regex) => format!("does not match /{}/", regex.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for BodyAssertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyAssertion::Contains(text) => write!(f, "contains {:?}", text),
+            BodyAssertion::NotContains(text) => write!(f, "not-contains {:?}", text),
+            BodyAssertion::Matches(regex) => write!(f, "matches /{}/", regex.as_str()),
+        }
+    }
+}
+
+impl PartialEq for BodyAssertion {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (BodyAssertion::Contains(a), BodyAssertion::Contains(b)) => a == b,
+            (BodyAssertion::NotContains(a), BodyAssertion::NotContains(b)) => a == b,
+            (BodyAssertion::Matches(a), BodyAssertion::Matches(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for BodyAssertion {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "<h1>Welcome</h1><p>Build v1.42</p>";
+
+    #[test]
+    fn test_contains() {
+        assert!(
+            BodyAssertion::Contains("Welcome".into())
+                .check(PAGE)
+                .is_ok()
+        );
+        assert_eq!(
+            BodyAssertion::Contains("Goodbye".into()).check(PAGE),
+            Err("body does not contain \"Goodbye\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_not_contains() {
+        assert!(
+            BodyAssertion::NotContains("Exception".into())
+                .check(PAGE)
+                .is_ok()
+        );
+        assert_eq!(
+            BodyAssertion::NotContains("Welcome".into()).check(PAGE),
+            Err("body contains \"Welcome\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(
+            BodyAssertion::matches(r"v\d+\.\d+")
+                .unwrap()
+                .check(PAGE)
+                .is_ok()
+        );
+        assert_eq!(
+            BodyAssertion::matches(r"v2\.\d+").unwrap().check(PAGE),
+            Err(r"body does not match /v2\.\d+/".to_string())
+        );
+        assert!(BodyAssertion::matches("(unclosed").is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+/// The leaf certificate a site presented during the TLS handshake.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    /// DNS names and IP addresses from the subject alternative name
+    /// extension.
+    pub subject_alt_names: Vec<String>,
+    pub not_before: DateTime<Local>,
+    pub not_after: DateTime<Local>,
+    /// Whether the certificate is valid for the host that was checked.
+    pub hostname_matches: bool,
+}
+
+impl CertificateInfo {
+    /// Parses a DER-encoded certificate presented by `host`.
+    pub(crate) fn from_der(der: &[u8], host: &str) -> Result<CertificateInfo, String> {
+        let (_, cert) = X509Certificate::from_der(der)
+            .map_err(|err| format!("invalid certificate: {}", err))?;
+
+        let mut names = Vec::new();
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(dns) => names.push(dns.to_string()),
+                    GeneralName::IPAddress(bytes) => {
+                        if let Some(ip) = ip_from_bytes(bytes) {
+                            names.push(ip.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        // Clients only fall back to the common name without SANs
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(str::to_string);
+        let hostname_matches = if names.is_empty() {
+            common_name.is_some_and(|cn| name_matches(&cn, host))
+        } else {
+            names.iter().any(|name| name_matches(name, host))
+        };
+
+        let validity = cert.validity();
+        Ok(CertificateInfo {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            subject_alt_names: names,
+            not_before: local_time(validity.not_before.timestamp())?,
+            not_after: local_time(validity.not_after.timestamp())?,
+            hostname_matches,
+        })
+    }
+
+    /// Whole days from `now` until the certificate expires; negative once it
+    /// has.
+    pub fn days_remaining(&self, now: DateTime<Local>) -> i64 {
+        (self.not_after - now).num_days()
+    }
+
+    pub fn is_expired(&self, now: DateTime<Local>) -> bool {
+        self.not_after < now
+    }
+
+    /// A problem that makes clients reject the certificate: it has expired,
+    /// is not valid yet, or was issued for another host.
+    pub fn problem(&self, host: &str, now: DateTime<Local>) -> Option<String> {
+        if self.is_expired(now) {
+            Some(format!(
+                "certificate expired on {}",
+                self.not_after.to_rfc3339()
+            ))
+        } else if now < self.not_before {
+            Some(format!(
+                "certificate is not valid until {}",
+                self.not_before.to_rfc3339()
+            ))
+        } else if !self.hostname_matches {
+            Some(format!(
+                "certificate for {} does not match host {}",
+                self.describe_names(),
+                host
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// A warning if the certificate expires within `warning_days` of `now`.
+    pub fn expiry_warning(&self, warning_days: u32, now: DateTime<Local>) -> Option<String> {
+        let days = self.days_remaining(now);
+        (!self.is_expired(now) && days < i64::from(warning_days)).then(|| {
+            format!(
+                "certificate expires in {} day{} on {}",
+                days,
+                if days == 1 { "" } else { "s" },
+                self.not_after.to_rfc3339()
+            )
+        })
+    }
+
+    fn describe_names(&self) -> String {
+        if self.subject_alt_names.is_empty() {
+            self.subject.clone()
+        } else {
+            self.subject_alt_names.join(", ")
+        }
+    }
+}
+
+/// Matches a certificate name against a host, allowing a `*` wildcard for
+/// exactly one leftmost label.
+fn name_matches(name: &str, host: &str) -> bool {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    if let (Ok(a), Ok(b)) = (name.parse::<IpAddr>(), host.parse::<IpAddr>()) {
+        return a == b;
+    }
+    match name.strip_prefix("*.") {
+        Some(suffix) => host
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest == suffix),
+        None => name == host,
+    }
+}
+
+fn ip_from_bytes(bytes: &[u8]) -> Option<IpAddr> {
+    match bytes.len() {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+        16 => Some(IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+        _ => None,
+    }
+}
+
+fn local_time(timestamp: i64) -> Result<DateTime<Local>, String> {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.with_timezone(&Local))
+        .ok_or_else(|| format!("certificate date out of range: {}", timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn certificate(names: &[&str], not_after: DateTime<Local>) -> CertificateInfo {
+        CertificateInfo {
+            subject: "CN=example".to_string(),
+            issuer: "CN=example".to_string(),
+            subject_alt_names: names.iter().map(|n| n.to_string()).collect(),
+            not_before: Local::now() - Duration::days(30),
+            not_after,
+            hostname_matches: true,
+        }
+    }
+
+    #[test]
+    fn test_name_matching() {
+        assert!(name_matches("example.com", "EXAMPLE.com"));
+        assert!(name_matches("*.example.com", "www.example.com"));
+        assert!(!name_matches("*.example.com", "example.com"));
+        assert!(!name_matches("*.example.com", "a.b.example.com"));
+        assert!(name_matches("127.0.0.1", "127.0.0.1"));
+        assert!(name_matches("::1", "[::1]"));
+        assert!(!name_matches("localhost", "127.0.0.1"));
+    }
+
+    #[test]
+    fn test_expiry_warning_within_configured_days() {
+        let now = Local::now();
+        let soon = certificate(&["a.example"], now + Duration::days(5) + Duration::hours(1));
+        assert_eq!(soon.days_remaining(now), 5);
+        let warning = soon.expiry_warning(14, now).unwrap();
+        assert!(
+            warning.starts_with("certificate expires in 5 days"),
+            "{}",
+            warning
+        );
+        assert_eq!(soon.expiry_warning(5, now), None);
+        assert_eq!(soon.problem("a.example", now), None);
+
+        let expired = certificate(&["a.example"], now - Duration::days(1));
+        assert!(expired.is_expired(now));
+        assert_eq!(expired.expiry_warning(14, now), None);
+        assert!(
+            expired
+                .problem("a.example", now)
+                .unwrap()
+                .starts_with("certificate expired on")
+        );
+    }
+
+    #[test]
+    fn test_mismatch_is_a_problem() {
+        let now = Local::now();
+        let mut cert = certificate(&["a.example", "*.a.example"], now + Duration::days(90));
+        cert.hostname_matches = false;
+        assert_eq!(
+            cert.problem("b.example", now),
+            Some(
+                "certificate for a.example, *.a.example does not match host b.example".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::status::{Failure, WebsiteStatus};
+use crate::target::Target;
+
+/// A boxed future, so that [`Check`] can be used as a trait object.
+pub(crate) type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// One kind of probe, such as an HTTP request or a TCP connect.
+///
+/// The scheduler only sees this trait: it hands each target to the check
+/// registered for the target's scheme and reports whatever comes back, so a
+/// new probe type is added by implementing `Check` and registering it with
+/// [`Checks::register`] where the checker builds its engine.
+pub(crate) trait Check: Send + Sync {
+    /// Probes `target`, returning its outcome and how long each phase took.
+    fn check<'a>(&'a self, target: &'a Target) -> BoxFuture<'a, WebsiteStatus>;
+}
+
+/// The checks available to a run, keyed by the URL scheme they handle.
+pub(crate) struct Checks {
+    by_scheme: HashMap<&'static str, Arc<dyn Check>>,
+}
+
+impl Checks {
+    /// Checks for `http` and `https`; other schemes are added with
+    /// [`Checks::register`].
+    pub fn new(http: impl Check + 'static) -> Self {
+        let http: Arc<dyn Check> = Arc::new(http);
+        let mut checks = Checks {
+            by_scheme: HashMap::new(),
+        };
+        checks.register("http", Arc::clone(&http));
+        checks.register("https", http);
+        checks
+    }
+
+    pub fn register(&mut self, scheme: &'static str, check: Arc<dyn Check>) {
+        self.by_scheme.insert(scheme, check);
+    }
+
+    /// Runs the check for the scheme of `target`'s URL.
+    pub async fn check(&self, target: &Target) -> WebsiteStatus {
+        let Some(scheme) = scheme(target.url()) else {
+            return unsupported(target, "URL has no scheme such as http:// or tcp://");
+        };
+        match self.by_scheme.get(scheme.as_str()) {
+            Some(check) => check.check(target).await,
+            None => {
+                let mut supported: Vec<&str> = self.by_scheme.keys().copied().collect();
+                supported.sort();
+                unsupported(
+                    target,
+                    &format!(
+                        "unsupported scheme '{}' (expected one of {})",
+                        scheme,
+                        supported.join(", ")
+                    ),
+                )
+            }
+        }
+    }
+}
+
+/// The lower-cased scheme of `url`, e.g. `tcp` for `tcp://db:5432`.
+pub(crate) fn scheme(url: &str) -> Option<String> {
+    let (scheme, _) = url.split_once("://")?;
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then(|| scheme.to_ascii_lowercase())
+}
+
+fn unsupported(target: &Target, message: &str) -> WebsiteStatus {
+    WebsiteStatus::new(
+        target,
+        None,
+        Some(Failure::Request(message.to_string())),
+        Duration::ZERO,
+        chrono::Local::now(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Succeeds with a fixed status code, whatever the target.
+    struct Fixed(u16);
+
+    impl Check for Fixed {
+        fn check<'a>(&'a self, target: &'a Target) -> BoxFuture<'a, WebsiteStatus> {
+            Box::pin(async move {
+                WebsiteStatus::new(
+                    target,
+                    Some(self.0),
+                    None,
+                    Duration::ZERO,
+                    chrono::Local::now(),
+                )
+            })
+        }
+    }
+
+    #[test]
+    fn test_scheme() {
+        assert_eq!(scheme("HTTPS://example.com"), Some("https".to_string()));
+        assert_eq!(scheme("tcp://db:5432"), Some("tcp".to_string()));
+        assert_eq!(scheme("example.com"), None);
+        assert_eq!(scheme("://example.com"), None);
+        assert_eq!(scheme("not a url://x"), None);
+    }
+
+    #[tokio::test]
+    async fn test_dispatches_on_scheme() {
+        let mut checks = Checks::new(Fixed(200));
+        checks.register("tcp", Arc::new(Fixed(1)));
+
+        let http = checks.check(&Target::new("http://example.com")).await;
+        assert_eq!(http.status_code(), Some(200));
+        let https = checks.check(&Target::new("HTTPS://example.com")).await;
+        assert_eq!(https.status_code(), Some(200));
+        let tcp = checks.check(&Target::new("tcp://db:5432")).await;
+        assert_eq!(tcp.status_code(), Some(1));
+
+        let ftp = checks.check(&Target::new("ftp://example.com")).await;
+        assert_eq!(
+            ftp.failure().map(Failure::message),
+            Some("unsupported scheme 'ftp' (expected one of http, https, tcp)")
+        );
+        let bare = checks.check(&Target::new("example.com")).await;
+        assert!(!bare.is_success());
+    }
+}
@@ -0,0 +1,953 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::sync::{Arc, mpsc};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use native_tls::Certificate;
+use reqwest::Method;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{self, Instant};
+
+use crate::check::Checks;
+use crate::dns::DnsCheck;
+use crate::expected::ExpectedStatus;
+use crate::http::{CheckSettings, HttpCheck, http_client};
+use crate::limits::{HostLimits, HostPermit, Limiter, host_key};
+use crate::redirect::RedirectPolicy;
+use crate::retry::RetryPolicy;
+use crate::shutdown::Shutdown;
+use crate::status::WebsiteStatus;
+use crate::target::Target;
+use crate::tcp::TcpCheck;
+
+/// How often the watch scheduler checks for a shutdown request while idle.
+const SHUTDOWN_POLL: Duration = Duration::from_millis(100);
+
+/// Checks targets concurrently on an async runtime, with at most
+/// [`workers`](CheckerBuilder::workers) checks in flight at once.
+///
+/// Build one with [`Checker::builder`]; a `Checker` can be reused for any
+/// number of runs. Each run gets its own runtime on a background thread and
+/// a single connection-pooled HTTP client shared by all of its checks.
+#[derive(Debug, Clone)]
+pub struct Checker {
+    workers: usize,
+    limits: HostLimits,
+    interval: Duration,
+    root_certificates: Vec<Vec<u8>>, // DER encoded
+    settings: CheckSettings,
+}
+
+/// Configures a [`Checker`].
+#[derive(Debug, Clone)]
+pub struct CheckerBuilder {
+    workers: usize,
+    limits: HostLimits,
+    method: Method,
+    headers: HeaderMap,
+    timeout: Duration,
+    retry: RetryPolicy,
+    interval: Duration,
+    max_body_bytes: usize,
+    expected_status: ExpectedStatus,
+    cert_warning_days: u32,
+    redirects: RedirectPolicy,
+    root_certificates: Vec<Vec<u8>>, // DER encoded
+}
+
+impl Default for CheckerBuilder {
+    fn default() -> Self {
+        CheckerBuilder {
+            workers: num_cpus::get(), // Default to number of logical CPU cores
+            limits: HostLimits::default(),
+            method: Method::GET,
+            headers: HeaderMap::new(),
+            timeout: Duration::from_secs(5),
+            retry: RetryPolicy::default(),
+            interval: Duration::from_secs(60),
+            max_body_bytes: 1024 * 1024,
+            expected_status: ExpectedStatus::default(),
+            cert_warning_days: 14,
+            redirects: RedirectPolicy::default(),
+            root_certificates: Vec::new(),
+        }
+    }
+}
+
+impl CheckerBuilder {
+    /// Maximum number of checks in flight at once. Values below one are
+    /// treated as one. Defaults to the number of logical CPU cores.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Maximum number of checks in flight at once against any single host
+    /// name. Unlimited (up to `workers`) by default; zero is treated as one.
+    pub fn max_per_host(mut self, max_per_host: usize) -> Self {
+        self.limits.per_host = Some(max_per_host.max(1));
+        self
+    }
+
+    /// Maximum number of checks in flight at once against any single IP
+    /// address, however many host names resolve to it. Unlimited by
+    /// default; zero is treated as one.
+    pub fn max_per_ip(mut self, max_per_ip: usize) -> Self {
+        self.limits.per_ip = Some(max_per_ip.max(1));
+        self
+    }
+
+    /// Maximum number of requests started per second against any single
+    /// host name; requests are spaced evenly rather than sent in bursts.
+    /// Unlimited by default. Non-positive rates are ignored.
+    pub fn host_rate(mut self, requests_per_second: f64) -> Self {
+        self.limits.host_rate = Some(requests_per_second).filter(|rate| *rate > 0.0);
+        self
+    }
+
+    /// HTTP method for targets that do not set their own. Defaults to `GET`.
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Adds a header sent with every request. Targets can replace it by
+    /// setting a header of the same name.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Timeout applied to each individual HTTP request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How many times a request is retried after a transport error or a
+    /// retryable status code. Defaults to zero.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retry = self.retry.with_retries(retries);
+        self
+    }
+
+    /// When and how often failed requests are retried. Replaces any earlier
+    /// call to [`retries`](CheckerBuilder::retries).
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Default re-check interval in watch mode for targets that do not set
+    /// their own. Zero is treated as one millisecond.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Maximum number of body bytes read to evaluate body assertions;
+    /// anything beyond it is ignored. Defaults to 1 MiB.
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Status codes that count as healthy for targets that do not set their
+    /// own. Defaults to `2xx`.
+    pub fn expected_status(mut self, expected_status: ExpectedStatus) -> Self {
+        self.expected_status = expected_status;
+        self
+    }
+
+    /// Results for HTTPS targets whose certificate expires within this many
+    /// days carry a warning. Defaults to 14.
+    pub fn cert_warning_days(mut self, days: u32) -> Self {
+        self.cert_warning_days = days;
+        self
+    }
+
+    /// How redirects are followed for targets that do not set their own
+    /// policy. Defaults to following up to ten, to any host.
+    pub fn redirect_policy(mut self, redirects: RedirectPolicy) -> Self {
+        self.redirects = redirects;
+        self
+    }
+
+    /// Trusts `certificate` as a root in addition to the system's, e.g. for
+    /// servers behind an internal CA.
+    pub fn root_certificate(mut self, certificate: Certificate) -> Self {
+        let der = certificate
+            .to_der()
+            .expect("a parsed certificate encodes as DER");
+        self.root_certificates.push(der);
+        self
+    }
+
+    pub fn build(self) -> Checker {
+        Checker {
+            workers: self.workers.max(1),
+            limits: self.limits,
+            interval: self.interval.max(Duration::from_millis(1)),
+            root_certificates: self.root_certificates,
+            settings: CheckSettings {
+                method: self.method,
+                headers: self.headers,
+                timeout: self.timeout,
+                retry: self.retry,
+                max_body_bytes: self.max_body_bytes,
+                expected_status: self.expected_status,
+                cert_warning_days: self.cert_warning_days,
+                redirects: self.redirects,
+            },
+        }
+    }
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        CheckerBuilder::default().build()
+    }
+}
+
+impl Checker {
+    pub fn builder() -> CheckerBuilder {
+        CheckerBuilder::default()
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub fn max_per_host(&self) -> Option<usize> {
+        self.limits.per_host
+    }
+
+    pub fn max_per_ip(&self) -> Option<usize> {
+        self.limits.per_ip
+    }
+
+    /// Requests per second allowed against each host name, if limited.
+    pub fn host_rate(&self) -> Option<f64> {
+        self.limits.host_rate
+    }
+
+    pub fn method(&self) -> &Method {
+        &self.settings.method
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.settings.headers
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.settings.timeout
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.settings.retry.retries()
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.settings.retry
+    }
+
+    pub fn max_body_bytes(&self) -> usize {
+        self.settings.max_body_bytes
+    }
+
+    pub fn expected_status(&self) -> &ExpectedStatus {
+        &self.settings.expected_status
+    }
+
+    pub fn cert_warning_days(&self) -> u32 {
+        self.settings.cert_warning_days
+    }
+
+    pub fn redirect_policy(&self) -> &RedirectPolicy {
+        &self.settings.redirects
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Checks every target and returns the results in completion order.
+    pub fn run<I>(&self, targets: I) -> Vec<WebsiteStatus>
+    where
+        I: IntoIterator,
+        I::Item: Into<Target>,
+    {
+        self.spawn(targets).collect()
+    }
+
+    /// Starts checking every target in the background and returns a stream
+    /// that yields each result as soon as its check finishes.
+    pub fn spawn<I>(&self, targets: I) -> Results
+    where
+        I: IntoIterator,
+        I::Item: Into<Target>,
+    {
+        // One queue per host, served round-robin so that a long run of URLs
+        // on one host does not crowd out the others
+        let mut queues: Vec<VecDeque<Target>> = Vec::new();
+        let mut positions: HashMap<Option<String>, usize> = HashMap::new();
+        for target in targets {
+            let target: Target = target.into();
+            let position = *positions.entry(host_key(target.url())).or_insert_with(|| {
+                queues.push(VecDeque::new());
+                queues.len() - 1
+            });
+            queues[position].push_back(target);
+        }
+
+        self.start(move |engine, results| async move {
+            let hosts = queues.iter().map(|queue| queue[0].url().to_string());
+            engine.limiter.resolve_all(hosts).await;
+
+            let mut checks = JoinSet::new();
+            let mut next = 0;
+            while !queues.is_empty() {
+                // Take the host's and its IP's budget before a global slot,
+                // so a throttled host or IP does not hold slots others could
+                // use, and wait for that slot before spawning, so a huge
+                // target list does not turn into as many idle tasks
+                let (position, host) = engine.next_host(&queues, next).await;
+                let permit = engine.acquire().await;
+
+                let target = queues[position]
+                    .pop_front()
+                    .expect("queues are never empty");
+                if queues[position].is_empty() {
+                    queues.remove(position);
+                    next = position;
+                } else {
+                    next = position + 1;
+                }
+
+                let engine = engine.clone();
+                let results = results.clone();
+                checks.spawn(async move {
+                    let status = engine.check(&target).await;
+                    drop((permit, host));
+                    // The receiving side may have gone away; nothing to do
+                    let _ = results.send(status);
+                });
+            }
+            while checks.join_next().await.is_some() {}
+        })
+    }
+
+    /// Checks every target repeatedly, each on its own interval, until
+    /// `shutdown` is triggered.
+    ///
+    /// Each target is re-scheduled one interval after its previous check
+    /// started, or immediately if that check took longer than the interval,
+    /// so a slow target is never checked twice at once. The returned stream
+    /// ends once in-flight checks have finished after shutdown.
+    pub fn watch<I>(&self, targets: I, shutdown: &Shutdown) -> Results
+    where
+        I: IntoIterator,
+        I::Item: Into<Target>,
+    {
+        let targets: Vec<Arc<Target>> = targets
+            .into_iter()
+            .map(|target| Arc::new(target.into()))
+            .collect();
+        let default_interval = self.interval;
+        let shutdown = shutdown.clone();
+
+        self.start(move |engine, results| async move {
+            let (done_tx, mut done_rx) = unbounded_channel::<Done>();
+
+            // Min-heap of (next due time, target index); every target is due now
+            let now = Instant::now();
+            let mut queue: BinaryHeap<Reverse<(Instant, usize)>> = (0..targets.len())
+                .map(|index| Reverse((now, index)))
+                .collect();
+            // When each target's in-flight check was handed out
+            let mut started = vec![now; targets.len()];
+
+            while !shutdown.is_triggered() {
+                // Start a check for every due target; each waits for a free
+                // slot on its own so the scheduler never blocks
+                let now = Instant::now();
+                while let Some(&Reverse((due, index))) = queue.peek() {
+                    if due > now {
+                        break;
+                    }
+                    queue.pop();
+                    started[index] = now;
+                    let engine = engine.clone();
+                    let target = Arc::clone(&targets[index]);
+                    let done = done_tx.clone();
+                    tokio::spawn(async move {
+                        // Take the IP and host budgets before a global slot,
+                        // so a throttled host does not hold slots others
+                        // could use
+                        let _host = engine.limiter.acquire(target.url()).await;
+                        let _permit = engine.acquire().await;
+                        let status = engine.check(&target).await;
+                        let _ = done.send(Done { index, status });
+                    });
+                }
+
+                // Sleep until the next target is due, waking early for
+                // completed checks and to notice shutdown promptly
+                let wait = match queue.peek() {
+                    Some(&Reverse((due, _))) => due.saturating_duration_since(now),
+                    None => SHUTDOWN_POLL,
+                };
+                if let Ok(Some(done)) = time::timeout(wait.min(SHUTDOWN_POLL), done_rx.recv()).await
+                {
+                    let index = done.index;
+                    let interval = targets[index].interval().unwrap_or(default_interval);
+                    queue.push(Reverse((started[index] + interval, index)));
+                    if results.send(done.status).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            // Stop handing out work and drain the checks still in flight
+            drop(done_tx);
+            while let Some(done) = done_rx.recv().await {
+                if results.send(done.status).is_err() {
+                    return;
+                }
+            }
+        })
+    }
+
+    /// Runs `schedule` on a fresh async runtime in a background thread and
+    /// returns the stream of results it sends.
+    ///
+    /// The runtime, and the connection pool of the client shared by its
+    /// checks, live exactly as long as the run.
+    fn start<F, Fut>(&self, schedule: F) -> Results
+    where
+        F: FnOnce(Engine, mpsc::Sender<WebsiteStatus>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()>,
+    {
+        let (tx, rx) = mpsc::channel();
+        let workers = self.workers;
+        let limiter = Arc::new(Limiter::new(self.limits.clone()));
+        let settings = Arc::new(self.settings.clone());
+        let root_certificates: Vec<Certificate> = self
+            .root_certificates
+            .iter()
+            .map(|der| Certificate::from_der(der).expect("root certificates were encoded as DER"))
+            .collect();
+
+        let handle = thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to start async runtime");
+            runtime.block_on(async move {
+                // Checks follow redirects themselves to record each hop
+                let client = http_client(&root_certificates).expect("Failed to set up TLS");
+                let mut checks = Checks::new(HttpCheck::new(client, Arc::clone(&settings)));
+                checks.register("tcp", Arc::new(TcpCheck::new(Arc::clone(&settings))));
+                checks.register("dns", Arc::new(DnsCheck::new(settings)));
+                let engine = Engine {
+                    checks: Arc::new(checks),
+                    in_flight: Arc::new(Semaphore::new(workers)),
+                    limiter,
+                };
+                schedule(engine, tx).await;
+            });
+        });
+
+        Results {
+            rx,
+            handle: Some(handle),
+        }
+    }
+}
+
+/// What every check in a run shares: the checks for each scheme (with one
+/// connection-pooled HTTP client), the limit on checks in flight and the
+/// per-host budgets.
+#[derive(Clone)]
+struct Engine {
+    checks: Arc<Checks>,
+    in_flight: Arc<Semaphore>,
+    limiter: Arc<Limiter>,
+}
+
+impl Engine {
+    /// Waits for a free in-flight slot, held until the permit is dropped.
+    async fn acquire(&self) -> OwnedSemaphorePermit {
+        Arc::clone(&self.in_flight)
+            .acquire_owned()
+            .await
+            .expect("in-flight semaphore is never closed")
+    }
+
+    /// Picks the first queue, starting at `next` and wrapping around, whose
+    /// host and its IP address have budget for another check, waiting until
+    /// one does.
+    async fn next_host(&self, queues: &[VecDeque<Target>], next: usize) -> (usize, HostPermit) {
+        loop {
+            let released = self.limiter.released();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            let mut ready: Option<Instant> = None;
+            for offset in 0..queues.len() {
+                let position = (next + offset) % queues.len();
+                let url = queues[position][0].url();
+                match self.limiter.try_acquire(url) {
+                    Ok(host) => return (position, host),
+                    Err(Some(at)) => ready = Some(ready.map_or(at, |r| r.min(at))),
+                    Err(None) => {}
+                }
+            }
+
+            // Every host is busy or over its rate
+            match ready {
+                Some(at) => {
+                    tokio::select! {
+                        _ = released => {}
+                        _ = time::sleep_until(at) => {}
+                    }
+                }
+                None => released.await,
+            }
+        }
+    }
+
+    async fn check(&self, target: &Target) -> WebsiteStatus {
+        self.checks.check(target).await
+    }
+}
+
+/// A finished watch check, tagged with the index of its target.
+struct Done {
+    index: usize,
+    status: WebsiteStatus,
+}
+
+/// Stream of results produced by [`Checker::spawn`] and [`Checker::watch`].
+///
+/// Iteration ends once every check has finished, at which point the
+/// background runtime has shut down.
+pub struct Results {
+    rx: mpsc::Receiver<WebsiteStatus>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Iterator for Results {
+    type Item = WebsiteStatus;
+
+    fn next(&mut self) -> Option<WebsiteStatus> {
+        match self.rx.recv() {
+            Ok(status) => Some(status),
+            Err(_) => {
+                // The run has finished; wait for its runtime to shut down
+                if let Some(handle) = self.handle.take() {
+                    handle.join().expect("Failed to join checker thread");
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockServer, Reply, self_signed};
+    use crate::status::Failure;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_checker_returns_one_result_per_target() {
+        let server = MockServer::start().route("/", [Reply::reset()]);
+        let checker = Checker::builder()
+            .workers(2)
+            .timeout(Duration::from_secs(1))
+            .build();
+        let results = checker.run([server.url("/").as_str(), "not a url", "ftp://"]);
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|s| !s.is_success()));
+    }
+
+    #[test]
+    fn test_flaky_target_recovers_within_retries() {
+        let server = MockServer::start().route(
+            "/",
+            [Reply::status(502), Reply::status(503), Reply::status(200)],
+        );
+        let checker = Checker::builder()
+            .retry_policy(RetryPolicy::new(2).with_base_delay(Duration::from_millis(10)))
+            .build();
+
+        let results = checker.run([server.url("/")]);
+        assert!(results[0].is_success());
+        assert_eq!(results[0].status_code(), Some(200));
+        assert_eq!(results[0].attempts().len(), 3);
+    }
+
+    #[test]
+    fn test_timeout_covers_slow_bodies() {
+        let slow = Reply::status(200)
+            .body("0123456789")
+            .slowly(5, Duration::from_millis(100));
+        let server = MockServer::start().route("/", [slow]);
+        let checker = Checker::builder()
+            .timeout(Duration::from_millis(200))
+            .build();
+
+        let results = checker.run([server.url("/")]);
+        // The headers arrived in time, but the body did not
+        assert_eq!(results[0].status_code(), Some(200));
+        assert!(matches!(results[0].failure(), Some(Failure::Request(_))));
+    }
+
+    #[test]
+    fn test_targets_send_their_method_headers_and_body() {
+        let server = MockServer::start()
+            .route("/api", [Reply::status(200).body("ok")])
+            .route("/health", [Reply::status(200).body("a large page")]);
+        let checker = Checker::builder()
+            .header(
+                HeaderName::from_static("user-agent"),
+                HeaderValue::from_static("status-checker"),
+            )
+            .build();
+        let post = Target::new(server.url("/api"))
+            .with_method(Method::POST)
+            .with_header(
+                HeaderName::from_static("content-type"),
+                HeaderValue::from_static("application/json"),
+            )
+            .with_header(
+                HeaderName::from_static("host"),
+                HeaderValue::from_static("api.internal"),
+            )
+            .with_body(r#"{"ping": true}"#);
+        let head = Target::new(server.url("/health")).with_method(Method::HEAD);
+
+        let results = checker.run([post, head]);
+        assert!(results.iter().all(WebsiteStatus::is_success));
+
+        let [request] = &server.requests("/api")[..] else {
+            panic!("expected one request");
+        };
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.header("host"), Some("api.internal"));
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert_eq!(request.header("user-agent"), Some("status-checker"));
+        assert_eq!(request.body, br#"{"ping": true}"#);
+        assert_eq!(server.requests("/health")[0].method, "HEAD");
+    }
+
+    #[test]
+    fn test_redirect_chains_are_recorded() {
+        let server = MockServer::start()
+            .route("/old", [Reply::redirect(301, "/moved")])
+            .route("/moved", [Reply::redirect(302, "/new")])
+            .route("/new", [Reply::status(200)]);
+        let checker = Checker::builder().build();
+
+        let target = Target::new(server.url("/old")).with_final_url(server.url("/new"));
+        let status = checker.run([target]).remove(0);
+        assert!(status.is_success(), "{:?}", status.failure());
+        let chain: Vec<_> = status
+            .redirect_chain()
+            .iter()
+            .map(|hop| (hop.url.clone(), hop.status_code))
+            .collect();
+        assert_eq!(
+            chain,
+            [
+                (server.url("/old"), Some(301)),
+                (server.url("/moved"), Some(302)),
+                (server.url("/new"), Some(200)),
+            ]
+        );
+        assert_eq!(status.final_url(), server.url("/new"));
+        assert_eq!(status.redirects(), 2);
+
+        let target = Target::new(server.url("/old")).with_final_url(server.url("/login"));
+        let status = checker.run([target]).remove(0);
+        assert_eq!(
+            status.failure(),
+            Some(&Failure::Assertion(format!(
+                "final URL is {}, expected {}",
+                server.url("/new"),
+                server.url("/login")
+            )))
+        );
+    }
+
+    #[test]
+    fn test_redirect_policies() {
+        let server = MockServer::start()
+            .route("/old", [Reply::redirect(301, "/moved")])
+            .route("/moved", [Reply::redirect(302, "/new")])
+            .route("/new", [Reply::status(200)])
+            .route("/away", [Reply::redirect(302, "http://example.invalid/")])
+            .route("/ping", [Reply::redirect(302, "/pong")])
+            .route("/pong", [Reply::redirect(302, "/ping")]);
+        let checker = Checker::builder()
+            .redirect_policy(RedirectPolicy::none())
+            .build();
+        let kind = |target: Target| {
+            let status = checker.run([target]).remove(0);
+            status.failure().map(|failure| failure.kind())
+        };
+
+        // Not following, the redirect itself is judged
+        let status = checker.run([Target::new(server.url("/old"))]).remove(0);
+        assert_eq!(status.status_code(), Some(301));
+        assert_eq!(status.redirect_chain().len(), 1);
+        let target = Target::new(server.url("/old")).with_expected_status("301".parse().unwrap());
+        assert_eq!(kind(target), None);
+
+        let follow =
+            |hops| Target::new(server.url("/old")).with_redirects(RedirectPolicy::follow(hops));
+        assert_eq!(kind(follow(2)), None);
+        let status = checker.run([follow(1)]).remove(0);
+        assert_eq!(
+            status.failure(),
+            Some(&Failure::Redirect(format!(
+                "more than 1 redirects, stopped at {}",
+                server.url("/moved")
+            )))
+        );
+
+        let same_host = RedirectPolicy::default().with_same_host(true);
+        let target = Target::new(server.url("/away")).with_redirects(same_host);
+        assert_eq!(kind(target), Some("redirect"));
+        let target = Target::new(server.url("/old")).with_redirects(same_host);
+        assert_eq!(kind(target), None);
+
+        let target = Target::new(server.url("/ping")).with_redirects(RedirectPolicy::default());
+        assert_eq!(kind(target), Some("redirect"));
+        assert_eq!(server.hits("/ping"), 1);
+    }
+
+    #[test]
+    fn test_see_other_redirects_become_get() {
+        let server = MockServer::start()
+            .route("/submit", [Reply::redirect(303, "/done")])
+            .route("/keep", [Reply::redirect(307, "/done")])
+            .route("/done", [Reply::status(200)]);
+        let checker = Checker::builder().build();
+        let post = |path| {
+            Target::new(server.url(path))
+                .with_method(Method::POST)
+                .with_header(
+                    HeaderName::from_static("content-type"),
+                    HeaderValue::from_static("application/json"),
+                )
+                .with_body("{}")
+        };
+
+        let results = checker.run([post("/submit")]);
+        assert!(results[0].is_success(), "{:?}", results[0].failure());
+        let results = checker.run([post("/keep")]);
+        assert!(results[0].is_success(), "{:?}", results[0].failure());
+
+        let requests = server.requests("/done");
+        let [see_other, temporary] = &requests[..] else {
+            panic!("expected two requests: {:?}", requests.len());
+        };
+        assert_eq!(see_other.method, "GET");
+        assert_eq!(see_other.header("content-type"), None);
+        assert!(see_other.body.is_empty());
+        assert_eq!(temporary.method, "POST");
+        assert_eq!(temporary.body, b"{}");
+    }
+
+    #[test]
+    fn test_timings_describe_the_connection_used() {
+        let target = MockServer::start().route("/new", [Reply::status(200)]);
+        // By name, so the last hop needs a DNS lookup the first does not
+        let by_name = target.url("/new").replace("127.0.0.1", "localhost");
+        let server = MockServer::start().route("/old", [Reply::redirect(301, &by_name)]);
+        let checker = Checker::builder().workers(1).build();
+
+        let results = checker.run([server.url("/old")]);
+        let timings = results[0].timings();
+        assert!(timings.dns_ms.is_some());
+        assert!(timings.connect_ms.is_some());
+        assert!(timings.ttfb_ms.is_some());
+        assert!(timings.download_ms.is_some());
+
+        // The second check reuses the first one's pooled connection
+        let (addr, _) = counting_server(2, Duration::ZERO);
+        let url = format!("http://{}/", addr);
+        let results = checker.run([&url, &url]);
+        assert_eq!(results[0].timings().dns_ms, None);
+        assert!(results[0].timings().connect_ms.is_some());
+        assert_eq!(results[1].timings().connect_ms, None);
+        assert!(results[1].timings().ttfb_ms.is_some());
+    }
+
+    /// Serves `200 OK` after `delay` on a thread per request, and returns
+    /// the server's address and the most requests it had in flight at once.
+    fn counting_server(requests: usize, delay: Duration) -> (String, Arc<AtomicUsize>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap().to_string();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let peak_seen = Arc::clone(&peak);
+        thread::spawn(move || {
+            for request in server.incoming_requests().take(requests) {
+                let in_flight = Arc::clone(&in_flight);
+                let peak = Arc::clone(&peak_seen);
+                thread::spawn(move || {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(delay);
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    request.respond(tiny_http::Response::empty(200)).unwrap();
+                });
+            }
+        });
+        (addr, peak)
+    }
+
+    #[test]
+    fn test_max_per_host_limits_requests_in_flight() {
+        let (addr, peak) = counting_server(6, Duration::from_millis(50));
+        let checker = Checker::builder().workers(6).max_per_host(2).build();
+        let urls: Vec<String> = (0..6).map(|i| format!("http://{}/{}", addr, i)).collect();
+
+        let results = checker.run(&urls);
+        assert!(results.iter().all(|s| s.is_success()));
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_host_rate_spaces_requests() {
+        let (addr, _) = counting_server(3, Duration::ZERO);
+        let checker = Checker::builder().workers(3).host_rate(10.0).build();
+        let urls: Vec<String> = (0..3).map(|i| format!("http://{}/{}", addr, i)).collect();
+
+        let start = std::time::Instant::now();
+        let results = checker.run(&urls);
+        assert!(results.iter().all(|s| s.is_success()));
+        // The first request goes out at once, the next two 100 ms apart
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_certificate_close_to_expiry_warns() {
+        let (cert, key) = self_signed(
+            &["localhost"],
+            chrono::Local::now() + chrono::Duration::days(6),
+        );
+        let server = MockServer::start_tls(&cert, &key).route("/", [Reply::status(200)]);
+        let checker = Checker::builder()
+            .root_certificate(Certificate::from_pem(cert.as_bytes()).unwrap())
+            .cert_warning_days(14)
+            .build();
+
+        let results = checker.run([server.url("/")]);
+        assert!(results[0].is_success(), "{:?}", results[0].failure());
+        let certificate = results[0].certificate().unwrap();
+        assert_eq!(certificate.subject_alt_names, ["localhost"]);
+        assert!(certificate.hostname_matches);
+        assert!(
+            results[0]
+                .warning()
+                .is_some_and(|w| w.starts_with("certificate expires in")),
+            "{:?}",
+            results[0].warning()
+        );
+
+        let relaxed = Checker::builder()
+            .root_certificate(Certificate::from_pem(cert.as_bytes()).unwrap())
+            .cert_warning_days(3)
+            .build();
+        assert_eq!(relaxed.run([server.url("/")])[0].warning(), None);
+    }
+
+    #[test]
+    fn test_certificate_for_another_host_fails() {
+        let far = chrono::Local::now() + chrono::Duration::days(90);
+        let (cert, key) = self_signed(&["other.example"], far);
+        let server = MockServer::start_tls(&cert, &key).route("/", [Reply::status(200)]);
+        let checker = Checker::builder()
+            .root_certificate(Certificate::from_pem(cert.as_bytes()).unwrap())
+            .build();
+
+        let results = checker.run([server.url("/")]);
+        let failure = results[0].failure().unwrap();
+        assert_eq!(failure.kind(), "certificate");
+        assert_eq!(
+            failure.message(),
+            "certificate for other.example does not match host localhost"
+        );
+        assert!(!results[0].certificate().unwrap().hostname_matches);
+    }
+
+    #[test]
+    fn test_certificate_comes_from_the_final_hop() {
+        let soon = chrono::Local::now() + chrono::Duration::days(6);
+        let (cert, key) = self_signed(&["localhost"], soon);
+        let secure = MockServer::start_tls(&cert, &key).route("/", [Reply::status(200)]);
+        let (other_cert, other_key) = self_signed(&["other.example"], soon);
+        let other = MockServer::start_tls(&other_cert, &other_key).route("/", [Reply::status(200)]);
+        let server = MockServer::start()
+            .route("/secure", [Reply::redirect(301, &secure.url("/"))])
+            .route("/other", [Reply::redirect(301, &other.url("/"))]);
+        let checker = Checker::builder()
+            .root_certificate(Certificate::from_pem(cert.as_bytes()).unwrap())
+            .root_certificate(Certificate::from_pem(other_cert.as_bytes()).unwrap())
+            .cert_warning_days(14)
+            .build();
+
+        // Plain HTTP redirecting to HTTPS still checks the certificate
+        let results = checker.run([server.url("/secure")]);
+        assert!(results[0].is_success(), "{:?}", results[0].failure());
+        assert_eq!(results[0].final_url(), secure.url("/"));
+        assert_eq!(
+            results[0].certificate().unwrap().subject_alt_names,
+            ["localhost"]
+        );
+        assert!(
+            results[0]
+                .warning()
+                .is_some_and(|w| w.starts_with("certificate expires in"))
+        );
+
+        // A certificate rejected on the redirected hop is judged for its host
+        let results = checker.run([server.url("/other")]);
+        assert_eq!(
+            results[0].failure().map(Failure::message),
+            Some("certificate for other.example does not match host localhost")
+        );
+        assert_eq!(
+            results[0].certificate().unwrap().subject_alt_names,
+            ["other.example"]
+        );
+    }
+
+    #[test]
+    fn test_watch_rechecks_until_shutdown() {
+        let checker = Checker::builder().workers(1).build();
+        let target = Target::new("not a url").with_interval(Duration::from_millis(20));
+        let shutdown = Shutdown::new();
+
+        let trigger = shutdown.clone();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            trigger.trigger();
+        });
+
+        let results: Vec<_> = checker.watch([target], &shutdown).collect();
+        stopper.join().unwrap();
+        assert!(results.len() > 2, "only {} checks ran", results.len());
+        assert!(results.iter().all(|s| s.url() == "not a url"));
+    }
+}
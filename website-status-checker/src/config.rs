@@ -0,0 +1,831 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use regex::Regex;
+use reqwest::Method;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+
+use crate::assertion::BodyAssertion;
+use crate::checker::CheckerBuilder;
+use crate::duration::parse_duration;
+use crate::expected::ExpectedStatus;
+use crate::outcome::FailThreshold;
+use crate::redirect::RedirectPolicy;
+use crate::retry::RetryPolicy;
+use crate::target::Target;
+
+/// Targets and check settings loaded from a file.
+///
+/// Three formats are understood, chosen by file extension:
+///
+/// - `.toml`, `.yaml` and `.yml` files describe targets in a structured
+///   form, with a `[defaults]` table that per-target values override.
+///   Header values and request bodies may use `${NAME}` to read a secret
+///   from an environment variable, and `body_file` paths are relative to
+///   the file.
+/// - Anything else is a plain list with one target per line, parsed with
+///   [`Target`]'s `FromStr` implementation. Blank lines and lines starting
+///   with `#` are ignored.
+///
+/// ```toml
+/// workers = 8
+/// max_per_host = 2
+/// host_rate = 5
+/// cert_warning_days = 21
+/// fail_threshold = "10%"
+///
+/// [alert]
+/// webhook = "https://hooks.example.com/status"
+/// after = 3
+///
+/// [defaults]
+/// timeout = "10s"
+/// retries = 1
+/// backoff = { delay = "200ms", max_delay = "5s", retry_on = [429, 503] }
+/// expect = "2xx"
+/// redirects = 5
+/// headers = { User-Agent = "status-checker" }
+///
+/// [[targets]]
+/// name = "API"
+/// url = "https://api.example.com/health"
+/// method = "HEAD"
+/// timeout = "3s"
+/// tags = ["critical"]
+/// interval = "30s"
+///
+/// [[targets]]
+/// name = "Login"
+/// url = "http://example.com/account"
+/// redirects = "same-host,3"
+/// final_url = "https://example.com/login"
+///
+/// [[targets]]
+/// name = "Search"
+/// url = "https://search.example.com/query"
+/// method = "POST"
+/// headers = { Authorization = "Bearer ${SEARCH_TOKEN}", Content-Type = "application/json" }
+/// body_file = "search-query.json"
+///
+/// [[targets]]
+/// name = "Cache"
+/// url = "tcp://cache.internal:6379"
+/// send = "PING\r\n"
+/// expect_banner = "+PONG"
+///
+/// [[targets]]
+/// name = "Mail DNS"
+/// url = "dns://example.com?type=MX"
+/// expect_answers = ["10 mx1.example.com", "20 mx2.example.com"]
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub workers: Option<usize>,
+    pub max_per_host: Option<usize>,
+    pub max_per_ip: Option<usize>,
+    pub host_rate: Option<f64>,
+    pub max_body_bytes: Option<usize>,
+    pub cert_warning_days: Option<u32>,
+    pub fail_threshold: Option<FailThreshold>,
+    /// Endpoint that up/down alerts are POSTed to.
+    pub webhook: Option<String>,
+    /// Consecutive failures before a target is reported down.
+    pub alert_after: Option<u32>,
+    /// Retries of a failed webhook delivery.
+    pub webhook_retries: Option<u32>,
+    pub method: Option<Method>,
+    pub headers: HeaderMap,
+    pub timeout: Option<Duration>,
+    pub retries: Option<u32>,
+    /// Backoff settings, if the file sets any; its retry count is `retries`.
+    pub retry: Option<RetryPolicy>,
+    pub expected_status: Option<ExpectedStatus>,
+    pub redirects: Option<RedirectPolicy>,
+    pub interval: Option<Duration>,
+    pub targets: Vec<Target>,
+}
+
+impl Config {
+    /// Loads a config file, picking the format from its extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, String> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+
+        // Paths in the file, such as `body_file`, are relative to it
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let config = match extension.to_ascii_lowercase().as_str() {
+            "toml" => toml::from_str::<RawConfig>(&contents)
+                .map_err(|err| err.to_string())
+                .and_then(|raw| raw.into_config(dir)),
+            "yaml" | "yml" => serde_yaml::from_str::<RawConfig>(&contents)
+                .map_err(|err| err.to_string())
+                .and_then(|raw| raw.into_config(dir)),
+            _ => Config::from_text(&contents),
+        };
+        let mut config = config.map_err(|err| format!("{}: {}", path.display(), err))?;
+        let source = path.display().to_string();
+        config.targets = config
+            .targets
+            .into_iter()
+            .map(|target| target.with_source(source.clone()))
+            .collect();
+        Ok(config)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Config, String> {
+        let raw: RawConfig = toml::from_str(contents).map_err(|err| err.to_string())?;
+        raw.into_config(Path::new(""))
+    }
+
+    pub fn from_yaml(contents: &str) -> Result<Config, String> {
+        let raw: RawConfig = serde_yaml::from_str(contents).map_err(|err| err.to_string())?;
+        raw.into_config(Path::new(""))
+    }
+
+    /// Parses a plain list of targets, one per line.
+    pub fn from_text(contents: &str) -> Result<Config, String> {
+        let mut targets = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                let target = line
+                    .parse()
+                    .map_err(|err| format!("line {}: {}", number + 1, err))?;
+                targets.push(target);
+            }
+        }
+        Ok(Config {
+            targets,
+            ..Config::default()
+        })
+    }
+
+    /// Applies the file's defaults to `builder`.
+    pub fn apply(&self, mut builder: CheckerBuilder) -> CheckerBuilder {
+        if let Some(workers) = self.workers {
+            builder = builder.workers(workers);
+        }
+        if let Some(max_per_host) = self.max_per_host {
+            builder = builder.max_per_host(max_per_host);
+        }
+        if let Some(max_per_ip) = self.max_per_ip {
+            builder = builder.max_per_ip(max_per_ip);
+        }
+        if let Some(host_rate) = self.host_rate {
+            builder = builder.host_rate(host_rate);
+        }
+        if let Some(max_body_bytes) = self.max_body_bytes {
+            builder = builder.max_body_bytes(max_body_bytes);
+        }
+        if let Some(cert_warning_days) = self.cert_warning_days {
+            builder = builder.cert_warning_days(cert_warning_days);
+        }
+        if let Some(method) = &self.method {
+            builder = builder.method(method.clone());
+        }
+        for (name, value) in &self.headers {
+            builder = builder.header(name.clone(), value.clone());
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(retry) = &self.retry {
+            builder = builder.retry_policy(retry.clone());
+        }
+        if let Some(retries) = self.retries {
+            builder = builder.retries(retries);
+        }
+        if let Some(expected_status) = &self.expected_status {
+            builder = builder.expected_status(expected_status.clone());
+        }
+        if let Some(redirects) = self.redirects {
+            builder = builder.redirect_policy(redirects);
+        }
+        if let Some(interval) = self.interval {
+            builder = builder.interval(interval);
+        }
+        builder
+    }
+}
+
+/// A config file as written, before values are validated.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    workers: Option<usize>,
+    max_per_host: Option<usize>,
+    max_per_ip: Option<usize>,
+    host_rate: Option<f64>,
+    max_body_bytes: Option<usize>,
+    cert_warning_days: Option<u32>,
+    fail_threshold: Option<Scalar>,
+    alert: Option<RawAlert>,
+    #[serde(default)]
+    defaults: RawDefaults,
+    #[serde(default)]
+    targets: Vec<RawTarget>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawDefaults {
+    method: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    timeout: Option<Scalar>,
+    retries: Option<u32>,
+    backoff: Option<RawBackoff>,
+    expect: Option<Scalar>,
+    redirects: Option<Scalar>,
+    interval: Option<Scalar>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAlert {
+    webhook: String,
+    after: Option<u32>,
+    retries: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBackoff {
+    delay: Option<Scalar>,
+    multiplier: Option<f64>,
+    max_delay: Option<Scalar>,
+    jitter: Option<f64>,
+    retry_on: Option<Vec<u16>>,
+    retry_after: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTarget {
+    url: String,
+    name: Option<String>,
+    method: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    body: Option<String>,
+    body_file: Option<String>,
+    timeout: Option<Scalar>,
+    retries: Option<u32>,
+    expect: Option<Scalar>,
+    redirects: Option<Scalar>,
+    final_url: Option<String>,
+    #[serde(default)]
+    contains: Vec<String>,
+    #[serde(default)]
+    not_contains: Vec<String>,
+    #[serde(default)]
+    matches: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    interval: Option<Scalar>,
+    send: Option<String>,
+    expect_banner: Option<String>,
+    #[serde(default)]
+    expect_answers: Vec<String>,
+}
+
+/// A value that may be written as a string or a bare number, such as
+/// `timeout = 5`, `fail_threshold = 0.5` or `timeout = "500ms"`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    Number(u64),
+    Float(f64),
+    Text(String),
+}
+
+impl Scalar {
+    fn text(&self) -> String {
+        match self {
+            Scalar::Number(number) => number.to_string(),
+            Scalar::Float(number) => number.to_string(),
+            Scalar::Text(text) => text.clone(),
+        }
+    }
+
+    fn duration(&self, key: &str) -> Result<Duration, String> {
+        if let Scalar::Float(number) = self
+            && number.fract() != 0.0
+        {
+            return Err(format!(
+                "{}: {} is not a whole number of seconds; write a duration such as 5, \"500ms\" or \"2m\"",
+                key, number
+            ));
+        }
+        let duration = parse_duration(&self.text()).map_err(|err| format!("{}: {}", key, err))?;
+        if duration.is_zero() {
+            return Err(format!("{} must be greater than zero", key));
+        }
+        Ok(duration)
+    }
+
+    fn expected_status(&self) -> Result<ExpectedStatus, String> {
+        self.text()
+            .parse()
+            .map_err(|err| format!("expect: {}", err))
+    }
+
+    fn redirects(&self) -> Result<RedirectPolicy, String> {
+        self.text()
+            .parse()
+            .map_err(|err| format!("redirects: {}", err))
+    }
+}
+
+impl RawConfig {
+    fn into_config(self, dir: &Path) -> Result<Config, String> {
+        if let Some(rate) = self.host_rate
+            && rate <= 0.0
+        {
+            return Err("host_rate must be greater than zero".to_string());
+        }
+        if let Some(alert) = &self.alert
+            && alert.after == Some(0)
+        {
+            return Err("alert.after must be at least 1".to_string());
+        }
+        let defaults = self.defaults;
+        let targets = self
+            .targets
+            .into_iter()
+            .enumerate()
+            .map(|(index, raw)| {
+                let label = raw.name.clone().unwrap_or_else(|| raw.url.clone());
+                raw.into_target(dir)
+                    .map_err(|err| format!("target {} ({}): {}", index + 1, label, err))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Config {
+            workers: self.workers,
+            max_per_host: self.max_per_host,
+            max_per_ip: self.max_per_ip,
+            host_rate: self.host_rate,
+            max_body_bytes: self.max_body_bytes,
+            cert_warning_days: self.cert_warning_days,
+            fail_threshold: self
+                .fail_threshold
+                .map(|t| t.text().parse())
+                .transpose()
+                .map_err(|err| format!("fail_threshold: {}", err))?,
+            webhook: self.alert.as_ref().map(|a| a.webhook.clone()),
+            alert_after: self.alert.as_ref().and_then(|a| a.after),
+            webhook_retries: self.alert.as_ref().and_then(|a| a.retries),
+            method: defaults.method.as_deref().map(parse_method).transpose()?,
+            headers: parse_headers(&defaults.headers)?,
+            timeout: defaults
+                .timeout
+                .map(|t| t.duration("timeout"))
+                .transpose()?,
+            retries: defaults.retries,
+            retry: defaults
+                .backoff
+                .map(|b| b.into_policy(defaults.retries.unwrap_or(0)))
+                .transpose()?,
+            expected_status: defaults.expect.map(|e| e.expected_status()).transpose()?,
+            redirects: defaults.redirects.map(|r| r.redirects()).transpose()?,
+            interval: defaults
+                .interval
+                .map(|i| i.duration("interval"))
+                .transpose()?,
+            targets,
+        })
+    }
+}
+
+impl RawBackoff {
+    fn into_policy(self, retries: u32) -> Result<RetryPolicy, String> {
+        let mut policy = RetryPolicy::new(retries);
+        if let Some(delay) = self.delay {
+            policy = policy.with_base_delay(delay.duration("backoff.delay")?);
+        }
+        if let Some(multiplier) = self.multiplier {
+            policy = policy.with_multiplier(multiplier);
+        }
+        if let Some(max_delay) = self.max_delay {
+            policy = policy.with_max_delay(max_delay.duration("backoff.max_delay")?);
+        }
+        if let Some(jitter) = self.jitter {
+            policy = policy.with_jitter(jitter);
+        }
+        if let Some(retry_on) = self.retry_on {
+            policy = policy.with_retry_on(retry_on);
+        }
+        if let Some(retry_after) = self.retry_after {
+            policy = policy.with_retry_after(retry_after);
+        }
+        Ok(policy)
+    }
+}
+
+impl RawTarget {
+    fn into_target(self, dir: &Path) -> Result<Target, String> {
+        let mut target = Target::new(self.url);
+        if let Some(name) = self.name {
+            target = target.with_name(name);
+        }
+        for tag in self.tags {
+            target = target.with_tag(tag);
+        }
+        if let Some(method) = self.method {
+            target = target.with_method(parse_method(&method)?);
+        }
+        for (name, value) in &parse_headers(&self.headers)? {
+            target = target.with_header(name.clone(), value.clone());
+        }
+        match (self.body, self.body_file) {
+            (Some(_), Some(_)) => return Err("set body or body_file, not both".to_string()),
+            (Some(body), None) => target = target.with_body(expand_env(&body)?),
+            (None, Some(file)) => {
+                let path = dir.join(file);
+                let body = fs::read(&path)
+                    .map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+                target = target.with_body(body);
+            }
+            (None, None) => {}
+        }
+        if let Some(timeout) = self.timeout {
+            target = target.with_timeout(timeout.duration("timeout")?);
+        }
+        if let Some(retries) = self.retries {
+            target = target.with_retries(retries);
+        }
+        if let Some(expect) = self.expect {
+            target = target.with_expected_status(expect.expected_status()?);
+        }
+        if let Some(redirects) = self.redirects {
+            target = target.with_redirects(redirects.redirects()?);
+        }
+        if let Some(url) = self.final_url {
+            target = target.with_final_url(url);
+        }
+        for text in self.contains {
+            target = target.with_assertion(BodyAssertion::Contains(text));
+        }
+        for text in self.not_contains {
+            target = target.with_assertion(BodyAssertion::NotContains(text));
+        }
+        for pattern in self.matches {
+            target = target.with_assertion(BodyAssertion::matches(&pattern)?);
+        }
+        if let Some(interval) = self.interval {
+            target = target.with_interval(interval.duration("interval")?);
+        }
+        if let Some(send) = self.send {
+            target = target.with_send(send);
+        }
+        if let Some(banner) = self.expect_banner {
+            target = target.with_expected_banner(banner);
+        }
+        for answer in self.expect_answers {
+            target = target.with_expected_answer(answer);
+        }
+        Ok(target)
+    }
+}
+
+pub(crate) fn parse_method(method: &str) -> Result<Method, String> {
+    Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+        .map_err(|_| format!("invalid method '{}'", method))
+}
+
+/// Header values may refer to environment variables, keeping secrets such
+/// as tokens out of the file.
+fn parse_headers(headers: &BTreeMap<String, String>) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("invalid header name '{}'", name))?;
+        let value = HeaderValue::from_str(&expand_env(value)?)
+            .map_err(|_| format!("invalid value for header '{}'", name))?;
+        map.append(name, value);
+    }
+    Ok(map)
+}
+
+/// Replaces each `${NAME}` in `value` with the environment variable `NAME`,
+/// failing if it is not set. `$${NAME}` stands for a literal `${NAME}`.
+pub(crate) fn expand_env(value: &str) -> Result<String, String> {
+    static VARIABLE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\$(\$?)\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+
+    let mut expanded = String::new();
+    let mut last = 0;
+    for captures in VARIABLE.captures_iter(value) {
+        let (whole, name) = (captures.get(0).unwrap(), &captures[2]);
+        expanded.push_str(&value[last..whole.start()]);
+        if captures[1].is_empty() {
+            let variable =
+                env::var(name).map_err(|_| format!("environment variable {} is not set", name))?;
+            expanded.push_str(&variable);
+        } else {
+            expanded.push_str(&whole.as_str()[1..]);
+        }
+        last = whole.end();
+    }
+    expanded.push_str(&value[last..]);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = r#"
+workers = 8
+max_per_host = 2
+host_rate = 0.5
+cert_warning_days = 21
+fail_threshold = "10%"
+
+[alert]
+webhook = "http://127.0.0.1:9000/hook"
+after = 3
+
+[defaults]
+timeout = "10s"
+retries = 1
+backoff = { delay = "200ms", jitter = 0, retry_on = [503] }
+expect = "2xx"
+redirects = 5
+headers = { User-Agent = "status-checker" }
+
+[[targets]]
+url = "https://example.com"
+
+[[targets]]
+name = "API"
+url = "https://api.example.com/health"
+method = "head"
+headers = { Authorization = "Bearer token" }
+timeout = 3
+retries = 0
+expect = 204
+redirects = "same-host,3"
+final_url = "https://api.example.com/health"
+contains = ["ok"]
+not_contains = ["Exception"]
+matches = ['v\d+']
+tags = ["critical", "api"]
+interval = "30s"
+
+[[targets]]
+url = "tcp://127.0.0.1:6379"
+send = "PING\r\n"
+expect_banner = "+PONG"
+
+[[targets]]
+url = "dns://example.com?type=A&server=127.0.0.1:5353"
+expect_answers = ["192.0.2.1", "192.0.2.2"]
+"#;
+
+    const YAML: &str = r#"
+workers: 8
+max_per_host: 2
+host_rate: 0.5
+cert_warning_days: 21
+fail_threshold: 10
+alert:
+  webhook: http://127.0.0.1:9000/hook
+  after: 3
+defaults:
+  timeout: 10s
+  retries: 1
+  backoff:
+    delay: 200ms
+    jitter: 0
+    retry_on: [503]
+  expect: 2xx
+  redirects: 5
+  headers:
+    User-Agent: status-checker
+targets:
+  - url: https://example.com
+  - name: API
+    url: https://api.example.com/health
+    method: head
+    headers:
+      Authorization: Bearer token
+    timeout: 3
+    retries: 0
+    expect: 204
+    redirects: same-host,3
+    final_url: https://api.example.com/health
+    contains: [ok]
+    not_contains: [Exception]
+    matches: ['v\d+']
+    tags: [critical, api]
+    interval: 30s
+  - url: tcp://127.0.0.1:6379
+    send: "PING\r\n"
+    expect_banner: +PONG
+  - url: dns://example.com?type=A&server=127.0.0.1:5353
+    expect_answers: [192.0.2.1, 192.0.2.2]
+"#;
+
+    fn assert_sample(config: &Config) {
+        assert_eq!(config.workers, Some(8));
+        assert_eq!(config.max_per_host, Some(2));
+        assert_eq!(config.host_rate, Some(0.5));
+        assert_eq!(config.cert_warning_days, Some(21));
+        assert_eq!(config.fail_threshold, Some(FailThreshold::new(10.0)));
+        assert_eq!(
+            config.webhook.as_deref(),
+            Some("http://127.0.0.1:9000/hook")
+        );
+        assert_eq!(config.alert_after, Some(3));
+        assert_eq!(config.webhook_retries, None);
+        assert_eq!(config.timeout, Some(Duration::from_secs(10)));
+        assert_eq!(config.retries, Some(1));
+        assert_eq!(
+            config.retry,
+            Some(
+                RetryPolicy::new(1)
+                    .with_base_delay(Duration::from_millis(200))
+                    .with_jitter(0.0)
+                    .with_retry_on([503])
+            )
+        );
+        assert_eq!(config.expected_status, Some("2xx".parse().unwrap()));
+        assert_eq!(config.redirects, Some(RedirectPolicy::follow(5)));
+        assert_eq!(config.headers["user-agent"], "status-checker");
+
+        assert_eq!(config.targets[0], Target::new("https://example.com"));
+
+        let api = &config.targets[1];
+        assert_eq!(api.name(), Some("API"));
+        assert_eq!(api.method(), Some(&Method::HEAD));
+        assert_eq!(api.headers()["authorization"], "Bearer token");
+        assert_eq!(api.timeout(), Some(Duration::from_secs(3)));
+        assert_eq!(api.retries(), Some(0));
+        assert_eq!(api.expected_status(), Some(&"204".parse().unwrap()));
+        assert_eq!(
+            api.redirects(),
+            Some(&RedirectPolicy::follow(3).with_same_host(true))
+        );
+        assert_eq!(api.final_url(), Some("https://api.example.com/health"));
+        assert_eq!(api.assertions().len(), 3);
+        assert_eq!(api.tags(), ["critical", "api"]);
+        assert_eq!(api.interval(), Some(Duration::from_secs(30)));
+
+        let cache = &config.targets[2];
+        assert_eq!(cache.send(), Some("PING\r\n"));
+        assert_eq!(cache.expected_banner(), Some("+PONG"));
+        assert_eq!(
+            config.targets[3].expected_answers(),
+            ["192.0.2.1", "192.0.2.2"]
+        );
+    }
+
+    #[test]
+    fn test_toml_config() {
+        assert_sample(&Config::from_toml(TOML).unwrap());
+    }
+
+    #[test]
+    fn test_yaml_config() {
+        assert_sample(&Config::from_yaml(YAML).unwrap());
+    }
+
+    #[test]
+    fn test_text_config() {
+        let config =
+            Config::from_text("# comment\n\nhttps://a.example\nhttps://b.example every 5s\n")
+                .unwrap();
+        assert_eq!(config.targets.len(), 2);
+        assert_eq!(config.targets[1].interval(), Some(Duration::from_secs(5)));
+
+        let err = Config::from_text("https://a.example\nhttps://b.example every\n").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{}", err);
+    }
+
+    #[test]
+    fn test_request_bodies_and_secrets() {
+        let dir = env::temp_dir().join(format!("status-checker-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("query.json"), r#"{"query": "status"}"#).unwrap();
+        let path = dir.join("sites.toml");
+        fs::write(
+            &path,
+            r#"
+[defaults]
+headers = { X-Checker = "${CARGO_PKG_NAME}" }
+
+[[targets]]
+url = "https://search.example.com/query"
+method = "POST"
+headers = { Authorization = "Bearer ${CARGO_PKG_NAME}", Host = "search.internal" }
+body_file = "query.json"
+
+[[targets]]
+url = "https://api.example.com/ping"
+method = "POST"
+body = '{"price": "$${NOT_A_SECRET}"}'
+"#,
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let source = path.display().to_string();
+        assert!(
+            config
+                .targets
+                .iter()
+                .all(|t| t.source() == Some(&source[..]))
+        );
+
+        let secret = env!("CARGO_PKG_NAME");
+        assert_eq!(config.headers["x-checker"], secret);
+        let search = &config.targets[0];
+        assert_eq!(search.method(), Some(&Method::POST));
+        assert_eq!(
+            search.headers()["authorization"],
+            format!("Bearer {}", secret).as_str()
+        );
+        assert_eq!(search.headers()["host"], "search.internal");
+        assert_eq!(search.body(), Some(&br#"{"query": "status"}"#[..]));
+        assert_eq!(
+            config.targets[1].body(),
+            Some(&br#"{"price": "${NOT_A_SECRET}"}"#[..])
+        );
+
+        let err = Config::from_toml(
+            "[[targets]]\nurl = \"x\"\nheaders = { Authorization = \"${STATUS_CHECKER_UNSET_VARIABLE}\" }\n",
+        )
+        .unwrap_err();
+        assert!(
+            err.contains("environment variable STATUS_CHECKER_UNSET_VARIABLE is not set"),
+            "{}",
+            err
+        );
+        assert!(
+            Config::from_toml("[[targets]]\nurl = \"x\"\nbody = \"a\"\nbody_file = \"b\"\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_invalid_values_name_the_target() {
+        let err = Config::from_toml("[[targets]]\nname = \"API\"\nurl = \"x\"\nexpect = \"9xx\"\n")
+            .unwrap_err();
+        assert!(err.contains("target 1 (API)"), "{}", err);
+
+        assert!(Config::from_toml("[[targets]]\nurl = \"x\"\ntimeout = \"soon\"\n").is_err());
+        assert!(
+            Config::from_toml("[[targets]]\nurl = \"x\"\nmethod = \"NOT A METHOD\"\n").is_err()
+        );
+        assert!(Config::from_toml("[[targets]]\nurl = \"x\"\nbogus = 1\n").is_err());
+    }
+
+    #[test]
+    fn test_fractional_numbers() {
+        let config =
+            Config::from_toml("fail_threshold = 0.5\n[defaults]\ntimeout = 2.0\n").unwrap();
+        assert_eq!(config.fail_threshold, Some(FailThreshold::new(0.5)));
+        assert_eq!(config.timeout, Some(Duration::from_secs(2)));
+
+        let err = Config::from_toml("[defaults]\ntimeout = 2.5\n").unwrap_err();
+        assert!(
+            err.starts_with("timeout: 2.5 is not a whole number"),
+            "{}",
+            err
+        );
+        assert!(err.contains("\"500ms\""), "{}", err);
+
+        let err = Config::from_yaml("targets:\n  - url: x\n    interval: 0.5\n").unwrap_err();
+        assert!(
+            err.contains("interval: 0.5 is not a whole number"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_apply_sets_checker_defaults() {
+        let config = Config::from_toml(TOML).unwrap();
+        let checker = config.apply(crate::Checker::builder()).build();
+        assert_eq!(checker.workers(), 8);
+        assert_eq!(checker.max_per_host(), Some(2));
+        assert_eq!(checker.max_per_ip(), None);
+        assert_eq!(checker.cert_warning_days(), 21);
+        assert_eq!(checker.timeout(), Duration::from_secs(10));
+        assert_eq!(checker.retries(), 1);
+        assert_eq!(
+            checker.retry_policy().base_delay(),
+            Duration::from_millis(200)
+        );
+        assert_eq!(checker.headers()["user-agent"], "status-checker");
+    }
+}
@@ -0,0 +1,382 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use hyper::Uri;
+use hyper::client::connect::{Connected, Connection};
+use hyper::service::Service;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpStream, lookup_host};
+use tokio_native_tls::{TlsConnector, TlsStream};
+
+use crate::certificate::CertificateInfo;
+use crate::check::BoxFuture;
+use crate::timing::{Timings, millis};
+
+/// Opens the HTTP client's connections, timing DNS resolution, TCP connect
+/// and TLS handshake, and keeping the certificate the server presented.
+///
+/// Both travel with the connection to every response it carries, so they
+/// always describe the connection a request was actually sent over.
+#[derive(Clone)]
+pub(crate) struct TimedConnector {
+    tls: TlsConnector,
+}
+
+impl TimedConnector {
+    /// A connector trusting the system's roots and `root_certificates`.
+    pub fn new(root_certificates: &[native_tls::Certificate]) -> native_tls::Result<Self> {
+        let mut builder = native_tls::TlsConnector::builder();
+        for certificate in root_certificates {
+            builder.add_root_certificate(certificate.clone());
+        }
+        Ok(TimedConnector {
+            tls: TlsConnector::from(builder.build()?),
+        })
+    }
+}
+
+impl Service<Uri> for TimedConnector {
+    type Response = TimedStream;
+    type Error = ConnectError;
+    type Future = BoxFuture<'static, Result<TimedStream, ConnectError>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), ConnectError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        Box::pin(connect(self.tls.clone(), uri))
+    }
+}
+
+/// Resolves, connects to and (for `https`) handshakes with the host of
+/// `uri`, recording how long each step took.
+async fn connect(tls: TlsConnector, uri: Uri) -> Result<TimedStream, ConnectError> {
+    let started_at = Instant::now();
+    let mut setup = Timings::default();
+    let https = uri.scheme_str() == Some("https");
+    // IPv6 hosts keep their brackets in a URI
+    let host = uri
+        .host()
+        .unwrap_or_default()
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+
+    let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => {
+            let start = Instant::now();
+            let addrs = lookup_host((host, port))
+                .await
+                .map_err(|err| ConnectError::new(format!("dns error: {}", err), &setup))?;
+            setup.dns_ms = Some(millis(start.elapsed()));
+            addrs.collect()
+        }
+    };
+
+    // Try each address in turn until one accepts
+    let start = Instant::now();
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to");
+    let mut stream = None;
+    for addr in addrs {
+        match TcpStream::connect(addr).await {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(err) => last_error = err,
+        }
+    }
+    let Some(stream) = stream else {
+        return Err(ConnectError::new(
+            format!("tcp connect error: {}", last_error),
+            &setup,
+        ));
+    };
+    setup.connect_ms = Some(millis(start.elapsed()));
+    let _ = stream.set_nodelay(true);
+
+    let mut certificate = None;
+    let io = if https {
+        let addr = stream.peer_addr();
+        let start = Instant::now();
+        match tls.connect(host, stream).await {
+            Ok(stream) => {
+                setup.tls_ms = Some(millis(start.elapsed()));
+                certificate = peer_certificate(stream.get_ref(), host);
+                Io::Tls(Box::new(stream))
+            }
+            Err(err) => {
+                let mut error = ConnectError::new(format!("tls handshake error: {}", err), &setup);
+                if let Ok(addr) = addr {
+                    error.certificate = inspect_certificate(addr, host).await;
+                }
+                return Err(error);
+            }
+        }
+    } else {
+        Io::Plain(stream)
+    };
+
+    Ok(TimedStream {
+        io,
+        info: ConnectionInfo {
+            setup,
+            started_at,
+            ready_at: Instant::now(),
+            certificate,
+            reported: Arc::new(AtomicBool::new(false)),
+        },
+    })
+}
+
+/// Reads the certificate at `addr` on a connection that accepts any
+/// certificate, to explain why the handshake that validates it failed.
+async fn inspect_certificate(addr: SocketAddr, host: &str) -> Option<CertificateInfo> {
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .danger_accept_invalid_hostnames(true)
+        .build()
+        .ok()?;
+    let stream = TcpStream::connect(addr).await.ok()?;
+    let tls = TlsConnector::from(connector)
+        .connect(host, stream)
+        .await
+        .ok()?;
+    peer_certificate(tls.get_ref(), host)
+}
+
+fn peer_certificate<S: io::Read + io::Write>(
+    stream: &native_tls::TlsStream<S>,
+    host: &str,
+) -> Option<CertificateInfo> {
+    let der = stream.peer_certificate().ok()??.to_der().ok()?;
+    CertificateInfo::from_der(&der, host).ok()
+}
+
+/// How a connection was opened, attached to every response it carries.
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionInfo {
+    setup: Timings,      // DNS, connect and TLS only
+    started_at: Instant, // When the connector was asked for the connection
+    ready_at: Instant,   // When the connection could carry a request
+    pub certificate: Option<CertificateInfo>,
+    reported: Arc<AtomicBool>, // Whether a response has claimed the setup
+}
+
+impl ConnectionInfo {
+    /// The timings of a request sent at `sent_at` whose response head
+    /// arrived at `received_at` over this connection.
+    ///
+    /// The setup phases belong to the first request that was waiting for
+    /// the connection to open, and time to first byte starts once it was
+    /// ready. Requests reusing the connection only report time to first
+    /// byte.
+    pub fn timings(&self, sent_at: Instant, received_at: Instant) -> Timings {
+        if self.started_at >= sent_at && !self.reported.swap(true, Ordering::Relaxed) {
+            Timings {
+                ttfb_ms: Some(millis(received_at.saturating_duration_since(self.ready_at))),
+                ..self.setup.clone()
+            }
+        } else {
+            Timings {
+                ttfb_ms: Some(millis(received_at.saturating_duration_since(sent_at))),
+                ..Timings::default()
+            }
+        }
+    }
+}
+
+/// Why a connection could not be opened, with the phases that completed
+/// and, for a failed handshake, the certificate the server presented.
+#[derive(Debug)]
+pub(crate) struct ConnectError {
+    message: String,
+    pub setup: Timings,
+    pub certificate: Option<CertificateInfo>,
+}
+
+impl ConnectError {
+    fn new(message: String, setup: &Timings) -> Self {
+        ConnectError {
+            message,
+            setup: setup.clone(),
+            certificate: None,
+        }
+    }
+
+    /// The connect error somewhere in the chain of sources of `err`.
+    pub fn find<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a ConnectError> {
+        let mut source = Some(err);
+        while let Some(err) = source {
+            if let Some(connect) = err.downcast_ref::<ConnectError>() {
+                return Some(connect);
+            }
+            source = err.source();
+        }
+        None
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for ConnectError {}
+
+/// A connection opened by [`TimedConnector`].
+pub(crate) struct TimedStream {
+    io: Io,
+    info: ConnectionInfo,
+}
+
+enum Io {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl Connection for TimedStream {
+    fn connected(&self) -> Connected {
+        Connected::new().extra(self.info.clone())
+    }
+}
+
+impl AsyncRead for TimedStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().io {
+            Io::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Io::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for TimedStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match &mut self.get_mut().io {
+            Io::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Io::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().io {
+            Io::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Io::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().io {
+            Io::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Io::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockServer, self_signed};
+    use chrono::Local;
+    use std::net::TcpListener;
+
+    async fn open(
+        url: &str,
+        roots: &[native_tls::Certificate],
+    ) -> Result<TimedStream, ConnectError> {
+        let mut connector = TimedConnector::new(roots).unwrap();
+        connector.call(url.parse().unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn test_plain_http_records_connect_only() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        let info = open(&url, &[]).await.unwrap().info;
+        assert_eq!(info.setup.dns_ms, None);
+        assert!(info.setup.connect_ms.is_some());
+        assert_eq!(info.setup.tls_ms, None);
+        assert_eq!(info.certificate, None);
+    }
+
+    #[tokio::test]
+    async fn test_resolves_domain_names() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let info = open(&format!("http://localhost:{}/", port), &[])
+            .await
+            .unwrap()
+            .info;
+        assert!(info.setup.dns_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_keeps_the_certificate_presented() {
+        let not_after = Local::now() + chrono::Duration::days(10);
+        let (cert, key) = self_signed(&["localhost", "10.0.0.1"], not_after);
+        let server = MockServer::start_tls(&cert, &key);
+        let root = native_tls::Certificate::from_pem(cert.as_bytes()).unwrap();
+
+        let info = open(&server.url("/"), &[root]).await.unwrap().info;
+        assert!(info.setup.tls_ms.is_some());
+        let certificate = info.certificate.unwrap();
+        assert_eq!(certificate.subject, "CN=mock server");
+        assert_eq!(certificate.subject_alt_names, ["localhost", "10.0.0.1"]);
+        assert!(certificate.hostname_matches);
+        assert_eq!(certificate.not_after.date_naive(), not_after.date_naive());
+
+        // Without trusting it, the handshake fails but the certificate is
+        // still read to explain why
+        let Err(err) = open(&server.url("/"), &[]).await else {
+            panic!("an untrusted certificate was accepted");
+        };
+        assert!(err.to_string().starts_with("tls handshake error"));
+        assert!(err.setup.connect_ms.is_some());
+        assert_eq!(err.certificate.unwrap().subject, "CN=mock server");
+    }
+
+    #[tokio::test]
+    async fn test_setup_goes_to_the_first_request_only() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let sent_at = Instant::now();
+        let info = open(&url, &[]).await.unwrap().info;
+        let received_at = Instant::now();
+
+        let first = info.timings(sent_at, received_at);
+        assert!(first.connect_ms.is_some());
+        assert!(first.ttfb_ms.is_some());
+
+        let reused = info.timings(Instant::now(), Instant::now());
+        assert_eq!(reused.connect_ms, None);
+        assert!(reused.ttfb_ms.is_some());
+
+        // Nothing listens on a port once its listener is dropped
+        drop(listener);
+        let Err(err) = open(&url, &[]).await else {
+            panic!("connected to a closed port");
+        };
+        assert!(err.to_string().starts_with("tcp connect error"));
+        assert_eq!(err.setup, Timings::default());
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::Serialize;
+use tiny_http::{Header, Response, Server};
+
+use crate::shutdown::Shutdown;
+use crate::status::WebsiteStatus;
+use crate::target::Target;
+
+/// Results kept per target for `/api/targets/{id}/history`.
+pub const HISTORY_LEN: usize = 100;
+
+/// The status page served at `/`. It polls `/api/targets` and needs nothing
+/// but the server itself.
+const PAGE: &str = include_str!("dashboard.html");
+
+/// The latest results of every target, served as JSON and as an HTML
+/// status page while checks run.
+///
+/// Cloning is cheap and every clone shares the same results, so one clone
+/// can be handed to [`Dashboard::serve`] while another records results.
+#[derive(Debug, Clone, Default)]
+pub struct Dashboard {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    targets: Vec<TargetState>,   // In the order targets were added
+    ids: HashMap<String, usize>, // Position in `targets` by URL
+}
+
+#[derive(Debug)]
+struct TargetState {
+    url: String,
+    name: Option<String>,
+    tags: Vec<String>,
+    history: VecDeque<WebsiteStatus>, // Oldest first, at most HISTORY_LEN
+}
+
+/// One target as listed by `/api/targets`.
+#[derive(Debug, Serialize)]
+struct TargetSummary<'a> {
+    id: usize,
+    url: &'a str,
+    name: Option<&'a str>,
+    tags: &'a [String],
+    /// `up`, `warning`, `down`, or `pending` before the first result.
+    state: &'static str,
+    checks: usize,
+    latest: Option<&'a WebsiteStatus>,
+}
+
+impl Dashboard {
+    /// A dashboard listing `targets` as pending until their first result.
+    pub fn new<'a>(targets: impl IntoIterator<Item = &'a Target>) -> Self {
+        let dashboard = Dashboard::default();
+        {
+            let mut state = dashboard.state.lock().unwrap();
+            for target in targets {
+                state.entry(target.url(), target.name(), target.tags());
+            }
+        }
+        dashboard
+    }
+
+    /// Makes `status` the latest result of its URL.
+    pub fn record(&self, status: &WebsiteStatus) {
+        let mut state = self.state.lock().unwrap();
+        let target = state.entry(status.url(), status.name(), status.tags());
+        if target.history.len() == HISTORY_LEN {
+            target.history.pop_front();
+        }
+        target.history.push_back(status.clone());
+    }
+
+    /// Every target with its latest result, as the JSON array served at
+    /// `/api/targets`.
+    pub fn targets_json(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let summaries: Vec<TargetSummary> = state
+            .targets
+            .iter()
+            .enumerate()
+            .map(|(id, target)| target.summary(id))
+            .collect();
+        serde_json::to_string(&summaries).expect("results serialize to JSON")
+    }
+
+    /// The recent results of target `id`, oldest first, as the JSON array
+    /// served at `/api/targets/{id}/history`; `None` for an unknown id.
+    pub fn history_json(&self, id: usize) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        let target = state.targets.get(id)?;
+        Some(serde_json::to_string(&target.history).expect("results serialize to JSON"))
+    }
+
+    /// Serves the status page at `/` and the JSON API under `/api` on
+    /// `addr` from a background thread until `shutdown` is triggered.
+    pub fn serve(
+        &self,
+        addr: impl ToSocketAddrs,
+        shutdown: &Shutdown,
+    ) -> io::Result<DashboardServer> {
+        let server = Server::http(addr).map_err(io::Error::other)?;
+        let local_addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| io::Error::other("dashboard server is not listening on TCP"))?;
+
+        let dashboard = self.clone();
+        let shutdown = shutdown.clone();
+        let handle = thread::spawn(move || {
+            while !shutdown.is_triggered() {
+                let request = match server.recv_timeout(Duration::from_millis(100)) {
+                    Ok(Some(request)) => request,
+                    Ok(None) => continue,
+                    Err(_) => break,
+                };
+                let path = request.url().split('?').next().unwrap_or_default();
+                let response = match dashboard.route(path) {
+                    Some((content_type, body)) => Response::from_string(body)
+                        .with_header(Header::from_bytes("Content-Type", content_type).unwrap()),
+                    None => Response::from_string("Not Found").with_status_code(404),
+                };
+                let _ = request.respond(response);
+            }
+        });
+
+        Ok(DashboardServer { local_addr, handle })
+    }
+
+    /// The content type and body for `path`, or `None` if nothing is there.
+    fn route(&self, path: &str) -> Option<(&'static str, String)> {
+        const JSON: &str = "application/json";
+        match path.trim_end_matches('/') {
+            "" | "/index.html" => Some(("text/html; charset=utf-8", PAGE.to_string())),
+            "/api/targets" => Some((JSON, self.targets_json())),
+            path => {
+                let id = path
+                    .strip_prefix("/api/targets/")?
+                    .strip_suffix("/history")?
+                    .parse()
+                    .ok()?;
+                self.history_json(id).map(|body| (JSON, body))
+            }
+        }
+    }
+}
+
+impl State {
+    /// The target with `url`, added at the end if it is new.
+    fn entry(&mut self, url: &str, name: Option<&str>, tags: &[String]) -> &mut TargetState {
+        let id = match self.ids.get(url) {
+            Some(&id) => id,
+            None => {
+                self.ids.insert(url.to_string(), self.targets.len());
+                self.targets.push(TargetState {
+                    url: url.to_string(),
+                    name: name.map(str::to_string),
+                    tags: tags.to_vec(),
+                    history: VecDeque::new(),
+                });
+                self.targets.len() - 1
+            }
+        };
+        &mut self.targets[id]
+    }
+}
+
+impl TargetState {
+    fn summary(&self, id: usize) -> TargetSummary<'_> {
+        let latest = self.history.back();
+        let state = match latest {
+            None => "pending",
+            Some(status) if !status.is_success() => "down",
+            Some(status) if status.warning().is_some() => "warning",
+            Some(_) => "up",
+        };
+        TargetSummary {
+            id,
+            url: &self.url,
+            name: self.name.as_deref(),
+            tags: &self.tags,
+            state,
+            checks: self.history.len(),
+            latest,
+        }
+    }
+}
+
+/// A running dashboard started by [`Dashboard::serve`].
+pub struct DashboardServer {
+    local_addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+impl DashboardServer {
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Waits for the server thread to exit after shutdown is triggered.
+    pub fn join(self) {
+        self.handle
+            .join()
+            .expect("Failed to join dashboard server thread");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::Failure;
+    use chrono::{Local, TimeZone};
+
+    fn status(url: &str, code: Option<u16>, failure: Option<Failure>) -> WebsiteStatus {
+        WebsiteStatus::new(
+            &Target::new(url),
+            code,
+            failure,
+            Duration::from_millis(20),
+            Local.timestamp_opt(1_700_000_000, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_targets_keep_their_latest_result() {
+        let targets = [
+            Target::new("https://a.example").with_name("A"),
+            Target::new("https://b.example"),
+        ];
+        let dashboard = Dashboard::new(&targets);
+        dashboard.record(&status("https://a.example", Some(200), None));
+        dashboard.record(&status(
+            "https://a.example",
+            None,
+            Some(Failure::Request("connection refused".into())),
+        ));
+
+        let json: serde_json::Value = serde_json::from_str(&dashboard.targets_json()).unwrap();
+        assert_eq!(json[0]["id"], 0);
+        assert_eq!(json[0]["name"], "A");
+        assert_eq!(json[0]["state"], "down");
+        assert_eq!(json[0]["checks"], 2);
+        assert_eq!(json[0]["latest"]["failure"]["kind"], "request");
+        assert_eq!(json[1]["url"], "https://b.example");
+        assert_eq!(json[1]["state"], "pending");
+        assert!(json[1]["latest"].is_null());
+
+        for _ in 0..HISTORY_LEN {
+            dashboard.record(&status("https://b.example", Some(200), None));
+        }
+        let history: serde_json::Value =
+            serde_json::from_str(&dashboard.history_json(1).unwrap()).unwrap();
+        assert_eq!(history.as_array().unwrap().len(), HISTORY_LEN);
+        assert_eq!(dashboard.history_json(2), None);
+    }
+
+    #[test]
+    fn test_serve_exposes_page_and_api() {
+        let dashboard = Dashboard::new(&[Target::new("https://a.example")]);
+        dashboard.record(&status("https://a.example", Some(200), None));
+        let shutdown = Shutdown::new();
+        let server = dashboard.serve("127.0.0.1:0", &shutdown).unwrap();
+        let base = format!("http://{}", server.local_addr());
+        let get = |path: &str| reqwest::blocking::get(format!("{}{}", base, path)).unwrap();
+
+        let page = get("/");
+        assert_eq!(page.status(), 200);
+        assert!(page.text().unwrap().contains("/api/targets"));
+
+        let json = |path: &str| -> serde_json::Value {
+            serde_json::from_str(&get(path).text().unwrap()).unwrap()
+        };
+        let targets = json("/api/targets");
+        assert_eq!(targets[0]["state"], "up");
+        assert_eq!(targets[0]["latest"]["status_code"], 200);
+
+        let history = json("/api/targets/0/history");
+        assert_eq!(history[0]["url"], "https://a.example");
+
+        assert_eq!(get("/api/targets/7/history").status(), 404);
+        assert_eq!(get("/other").status(), 404);
+
+        shutdown.trigger();
+        server.join();
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::BTreeSet;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use hickory_resolver::TokioResolver;
+use hickory_resolver::config::{ConnectionConfig, NameServerConfig, ResolveHosts, ResolverConfig};
+use hickory_resolver::net::NetError;
+use hickory_resolver::net::runtime::TokioRuntimeProvider;
+use hickory_resolver::proto::rr::{RData, RecordType};
+use reqwest::Url;
+use tokio::time;
+
+use crate::check::{BoxFuture, Check};
+use crate::http::CheckSettings;
+use crate::retry::Attempt;
+use crate::status::{Failure, WebsiteStatus};
+use crate::target::Target;
+use crate::timing::{Timings, millis};
+
+/// Record types a `dns://` target can ask for.
+const RECORD_TYPES: [RecordType; 5] = [
+    RecordType::A,
+    RecordType::AAAA,
+    RecordType::CNAME,
+    RecordType::MX,
+    RecordType::TXT,
+];
+
+/// Checks `dns://name?type=MX&server=1.1.1.1` targets by resolving the name
+/// and comparing the answers with the target's expected set.
+pub(crate) struct DnsCheck {
+    settings: Arc<CheckSettings>,
+}
+
+impl DnsCheck {
+    pub fn new(settings: Arc<CheckSettings>) -> Self {
+        DnsCheck { settings }
+    }
+}
+
+impl Check for DnsCheck {
+    fn check<'a>(&'a self, target: &'a Target) -> BoxFuture<'a, WebsiteStatus> {
+        Box::pin(check_dns(target, &self.settings))
+    }
+}
+
+/// What a `dns://` URL asks for.
+#[derive(Debug, PartialEq, Eq)]
+struct Question {
+    name: String,
+    record_type: RecordType,
+    server: Option<SocketAddr>, // The system's resolvers if unset
+}
+
+impl Question {
+    fn parse(url: &str) -> Result<Question, String> {
+        let parsed = Url::parse(url).map_err(|err| err.to_string())?;
+        let name = parsed
+            .host_str()
+            .filter(|name| !name.is_empty())
+            .ok_or("missing name to resolve")?
+            .trim_end_matches('.')
+            .to_ascii_lowercase();
+
+        let mut question = Question {
+            name,
+            record_type: RecordType::A,
+            server: None,
+        };
+        for (key, value) in parsed.query_pairs() {
+            match key.as_ref() {
+                "type" => {
+                    question.record_type = value
+                        .to_ascii_uppercase()
+                        .parse()
+                        .ok()
+                        .filter(|record_type| RECORD_TYPES.contains(record_type))
+                        .ok_or_else(|| {
+                            format!(
+                                "unsupported record type '{}' (expected A, AAAA, CNAME, MX or TXT)",
+                                value
+                            )
+                        })?;
+                }
+                "server" => question.server = Some(parse_server(&value)?),
+                _ => return Err(format!("unknown option '{}'", key)),
+            }
+        }
+        Ok(question)
+    }
+}
+
+/// A resolver address, with port 53 unless one is given.
+fn parse_server(server: &str) -> Result<SocketAddr, String> {
+    server
+        .parse::<SocketAddr>()
+        .or_else(|_| server.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+        .map_err(|_| format!("invalid resolver address '{}'", server))
+}
+
+async fn check_dns(target: &Target, settings: &CheckSettings) -> WebsiteStatus {
+    let start = Instant::now();
+    let question = match Question::parse(target.url()) {
+        Ok(question) => question,
+        Err(err) => {
+            let failure = Failure::Request(format!("invalid DNS target {}: {}", target.url(), err));
+            return WebsiteStatus::new(
+                target,
+                None,
+                Some(failure),
+                start.elapsed(),
+                chrono::Local::now(),
+            );
+        }
+    };
+    let timeout = target.timeout().unwrap_or(settings.timeout);
+    let retry = match target.retries() {
+        Some(retries) => settings.retry.clone().with_retries(retries),
+        None => settings.retry.clone(),
+    };
+
+    let mut attempts = Vec::new();
+    let (timings, result) = loop {
+        let attempt_start = Instant::now();
+        let result = match time::timeout(timeout, lookup(&question, timeout)).await {
+            Ok(result) => result,
+            Err(_) => Err(Failure::Request(format!(
+                "timed out after {} ms",
+                millis(timeout)
+            ))),
+        };
+        let elapsed = attempt_start.elapsed();
+        let timings = Timings {
+            dns_ms: Some(millis(elapsed)),
+            ..Timings::default()
+        };
+
+        // An answer, even an empty one, is what the server will say again
+        let attempt = match &result {
+            Ok(_) => Attempt::connected(elapsed),
+            Err(failure) => Attempt::error(failure.message().to_string(), elapsed),
+        };
+        if result.is_ok() || attempts.len() as u32 >= retry.retries() {
+            attempts.push(attempt);
+            break (timings, result);
+        }
+        let delay = retry.delay(attempts.len() as u32 + 1, None);
+        attempts.push(attempt.with_retry_delay(delay));
+        time::sleep(delay).await;
+    };
+
+    let (failure, answers) = match result {
+        Ok(answers) => (judge_answers(target, &question, &answers), Some(answers)),
+        Err(failure) => (Some(failure), None),
+    };
+    let status = WebsiteStatus::new(target, None, failure, start.elapsed(), chrono::Local::now())
+        .with_timings(timings, attempts);
+    match answers {
+        Some(answers) => status.with_answers(answers),
+        None => status,
+    }
+}
+
+/// Resolves `question`, returning its answers as text. A name without
+/// records of the type asked for has no answers rather than failing.
+async fn lookup(question: &Question, timeout: Duration) -> Result<Vec<String>, Failure> {
+    let failed = |err: NetError| {
+        Failure::Request(format!(
+            "{} lookup of {} failed: {}",
+            question.record_type, question.name, err
+        ))
+    };
+    let mut builder = match question.server {
+        Some(server) => {
+            let mut udp = ConnectionConfig::udp();
+            udp.port = server.port();
+            let mut tcp = ConnectionConfig::tcp();
+            tcp.port = server.port();
+            let config = ResolverConfig::from_name_servers(vec![NameServerConfig::new(
+                server.ip(),
+                true,
+                vec![udp, tcp],
+            )]);
+            TokioResolver::builder_with_config(config, TokioRuntimeProvider::default())
+        }
+        None => TokioResolver::builder_tokio().map_err(failed)?,
+    };
+    // Every check asks the server afresh, once; retries are ours to make
+    let options = builder.options_mut();
+    options.timeout = timeout;
+    options.attempts = 1;
+    options.cache_size = 0;
+    options.use_hosts_file = ResolveHosts::Never;
+    let resolver = builder.build().map_err(failed)?;
+
+    // A trailing dot stops the resolver trying search domains first
+    let lookup = match resolver
+        .lookup(format!("{}.", question.name), question.record_type)
+        .await
+    {
+        Ok(lookup) => lookup,
+        Err(err) if err.is_no_records_found() => return Ok(Vec::new()),
+        Err(err) => return Err(failed(err)),
+    };
+    Ok(lookup
+        .answers()
+        .iter()
+        .filter(|record| record.record_type() == question.record_type)
+        .filter_map(|record| describe(&record.data))
+        .collect())
+}
+
+/// The text form of a record: an address, a name without the trailing dot,
+/// `preference exchange` for MX, or the joined strings of a TXT record.
+fn describe(data: &RData) -> Option<String> {
+    let name = |name: &dyn ToString| name.to_string().trim_end_matches('.').to_string();
+    match data {
+        RData::A(a) => Some(a.0.to_string()),
+        RData::AAAA(aaaa) => Some(aaaa.0.to_string()),
+        RData::CNAME(cname) => Some(name(&cname.0)),
+        RData::MX(mx) => Some(format!("{} {}", mx.preference, name(&mx.exchange))),
+        RData::TXT(txt) => Some(
+            txt.txt_data
+                .iter()
+                .map(|part| String::from_utf8_lossy(part))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Fails if the target expects answers and `answers` is not exactly that
+/// set, or if a name has no records of the type asked for at all.
+fn judge_answers(target: &Target, question: &Question, answers: &[String]) -> Option<Failure> {
+    if target.expected_answers().is_empty() {
+        return answers.is_empty().then(|| {
+            Failure::Request(format!(
+                "{} has no {} records",
+                question.name, question.record_type
+            ))
+        });
+    }
+
+    let normalize = |answers: &[String]| -> BTreeSet<String> {
+        answers
+            .iter()
+            .map(|answer| normalize_answer(answer, question.record_type))
+            .collect()
+    };
+    let expected = normalize(target.expected_answers());
+    let actual = normalize(answers);
+    (expected != actual).then(|| {
+        let list = |set: &BTreeSet<String>| {
+            if set.is_empty() {
+                "none".to_string()
+            } else {
+                set.iter().cloned().collect::<Vec<_>>().join(", ")
+            }
+        };
+        Failure::Assertion(format!(
+            "expected {} answers {} but got {}",
+            question.record_type,
+            list(&expected),
+            list(&actual)
+        ))
+    })
+}
+
+/// Makes equivalent answers compare equal: addresses in canonical form and
+/// names without case or a trailing dot. TXT records are compared as is.
+fn normalize_answer(answer: &str, record_type: RecordType) -> String {
+    let answer = answer.trim();
+    if record_type == RecordType::TXT {
+        return answer.to_string();
+    }
+    match answer.parse::<IpAddr>() {
+        Ok(ip) => ip.to_string(),
+        Err(_) => answer.trim_end_matches('.').to_ascii_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockDns;
+    use crate::{Checker, RetryPolicy};
+
+    fn check(target: Target) -> WebsiteStatus {
+        Checker::builder()
+            .timeout(Duration::from_secs(2))
+            .retry_policy(RetryPolicy::new(0).with_base_delay(Duration::ZERO))
+            .build()
+            .run([target])
+            .remove(0)
+    }
+
+    fn server() -> MockDns {
+        MockDns::start()
+            .record("example.test", "A", "192.0.2.1")
+            .record("example.test", "A", "192.0.2.2")
+            .record("example.test", "AAAA", "2001:db8::1")
+            .record("www.example.test", "CNAME", "example.test")
+            .record("example.test", "MX", "10 mx1.example.test")
+            .record("example.test", "TXT", "v=spf1 -all")
+    }
+
+    #[test]
+    fn test_parse_question() {
+        assert_eq!(
+            Question::parse("dns://Example.com.?type=mx&server=1.1.1.1").unwrap(),
+            Question {
+                name: "example.com".to_string(),
+                record_type: RecordType::MX,
+                server: Some("1.1.1.1:53".parse().unwrap()),
+            }
+        );
+        let question = Question::parse("dns://example.com?server=[::1]:5353").unwrap();
+        assert_eq!(question.record_type, RecordType::A);
+        assert_eq!(question.server, Some("[::1]:5353".parse().unwrap()));
+
+        assert!(Question::parse("dns://example.com?type=SOA").is_err());
+        assert!(Question::parse("dns://example.com?server=resolver").is_err());
+        assert!(Question::parse("dns://example.com?ttl=5").is_err());
+    }
+
+    #[test]
+    fn test_resolves_each_record_type() {
+        let dns = server();
+        let answers = |record_type: &str| {
+            let url = format!(
+                "dns://example.test?type={}&server={}",
+                record_type,
+                dns.addr()
+            );
+            let status = check(Target::new(url));
+            assert!(status.is_success(), "{:?}", status.failure());
+            assert!(status.timings().dns_ms.is_some());
+            let mut answers = status.answers().unwrap().to_vec();
+            answers.sort();
+            answers
+        };
+        assert_eq!(answers("A"), ["192.0.2.1", "192.0.2.2"]);
+        assert_eq!(answers("AAAA"), ["2001:db8::1"]);
+        assert_eq!(answers("MX"), ["10 mx1.example.test"]);
+        assert_eq!(answers("TXT"), ["v=spf1 -all"]);
+
+        let url = format!("dns://www.example.test?type=CNAME&server={}", dns.addr());
+        assert_eq!(
+            check(Target::new(url)).answers(),
+            Some(&["example.test".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn test_expected_answers() {
+        let dns = server();
+        let url = format!("dns://example.test?server={}", dns.addr());
+
+        let matching = Target::new(&url)
+            .with_expected_answer("192.0.2.2")
+            .with_expected_answer("192.0.2.1");
+        assert!(check(matching).is_success());
+
+        let changed = Target::new(&url).with_expected_answer("192.0.2.1");
+        assert_eq!(
+            check(changed).failure(),
+            Some(&Failure::Assertion(
+                "expected A answers 192.0.2.1 but got 192.0.2.1, 192.0.2.2".to_string()
+            ))
+        );
+
+        let url = format!("dns://missing.example.test?server={}", dns.addr());
+        let gone = check(Target::new(&url).with_expected_answer("192.0.2.1"));
+        assert_eq!(
+            gone.failure().map(Failure::message),
+            Some("expected A answers 192.0.2.1 but got none")
+        );
+        assert_eq!(gone.answers(), Some(&[][..]));
+        let status = check(Target::new(url));
+        assert_eq!(
+            status.failure().map(Failure::message),
+            Some("missing.example.test has no A records")
+        );
+    }
+
+    #[test]
+    fn test_unanswered_lookups_are_retried() {
+        let dns = server();
+        let addr = dns.addr();
+        drop(dns);
+
+        let url = format!("dns://example.test?server={}", addr);
+        let target = Target::new(url)
+            .with_timeout(Duration::from_millis(200))
+            .with_retries(1);
+        let status = check(target);
+        assert_eq!(status.failure().map(Failure::kind), Some("request"));
+        assert_eq!(status.attempts().len(), 2);
+        assert_eq!(status.answers(), None);
+    }
+
+    #[test]
+    fn test_normalize_answer() {
+        assert_eq!(
+            normalize_answer("2001:DB8:0::1", RecordType::AAAA),
+            "2001:db8::1"
+        );
+        assert_eq!(
+            normalize_answer("Mail.Example.com.", RecordType::CNAME),
+            "mail.example.com"
+        );
+        assert_eq!(normalize_answer("Key=Value", RecordType::TXT), "Key=Value");
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// The set of HTTP status codes that count as a healthy response.
+///
+/// Parsed from a comma-separated list of exact codes (`200`), status classes
+/// (`2xx`) and inclusive ranges (`200-299`), e.g. `2xx,301`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedStatus {
+    ranges: Vec<RangeInclusive<u16>>,
+    spec: String, // The text it was parsed from, for display
+}
+
+impl ExpectedStatus {
+    /// Whether `code` is one of the expected codes.
+    pub fn matches(&self, code: u16) -> bool {
+        self.ranges.iter().any(|range| range.contains(&code))
+    }
+}
+
+impl Default for ExpectedStatus {
+    /// Any `2xx` code.
+    fn default() -> Self {
+        "2xx".parse().unwrap()
+    }
+}
+
+impl FromStr for ExpectedStatus {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = |item: &str| format!("invalid status code '{}' in '{}'", item, spec);
+        let code = |item: &str| -> Result<u16, String> {
+            match item.parse() {
+                Ok(code @ 100..=599) => Ok(code),
+                _ => Err(invalid(item)),
+            }
+        };
+
+        let mut ranges = Vec::new();
+        for item in spec.split(',').map(str::trim) {
+            let range = if let Some(class) = item.strip_suffix("xx") {
+                let class: u16 = match class.parse() {
+                    Ok(class @ 1..=5) => class,
+                    _ => return Err(invalid(item)),
+                };
+                class * 100..=class * 100 + 99
+            } else if let Some((low, high)) = item.split_once('-') {
+                let (low, high) = (code(low.trim())?, code(high.trim())?);
+                if low > high {
+                    return Err(invalid(item));
+                }
+                low..=high
+            } else {
+                let code = code(item)?;
+                code..=code
+            };
+            ranges.push(range);
+        }
+
+        Ok(ExpectedStatus {
+            ranges,
+            spec: spec.split(',').map(str::trim).collect::<Vec<_>>().join(","),
+        })
+    }
+}
+
+impl fmt::Display for ExpectedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_code() {
+        let expected: ExpectedStatus = "200".parse().unwrap();
+        assert!(expected.matches(200));
+        assert!(!expected.matches(201));
+    }
+
+    #[test]
+    fn test_class_and_ranges() {
+        let expected: ExpectedStatus = "2xx, 301, 400-404".parse().unwrap();
+        assert!(expected.matches(204));
+        assert!(expected.matches(301));
+        assert!(expected.matches(404));
+        assert!(!expected.matches(302));
+        assert!(!expected.matches(500));
+        assert_eq!(expected.to_string(), "2xx,301,400-404");
+    }
+
+    #[test]
+    fn test_default_is_2xx() {
+        let expected = ExpectedStatus::default();
+        assert!(expected.matches(200) && expected.matches(299));
+        assert!(!expected.matches(301) && !expected.matches(404) && !expected.matches(500));
+    }
+
+    #[test]
+    fn test_rejects_invalid_specs() {
+        for spec in [
+            "", "abc", "6xx", "0xx", "99", "600", "300-200", "2xx,", "200-",
+        ] {
+            assert!(
+                spec.parse::<ExpectedStatus>().is_err(),
+                "accepted '{}'",
+                spec
+            );
+        }
+    }
+}
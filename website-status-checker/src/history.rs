@@ -0,0 +1,291 @@
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::status::WebsiteStatus;
+
+/// Schema changes, applied in order. The database's `user_version` records
+/// how many have been applied; append new steps, never edit old ones.
+const MIGRATIONS: &[&str] = &[
+    // 1: one row per check, with the columns queries filter on broken out
+    // and the full result kept as JSON so it can be read back losslessly
+    "CREATE TABLE checks (
+        id INTEGER PRIMARY KEY,
+        url TEXT NOT NULL,
+        name TEXT,
+        checked_at INTEGER NOT NULL,
+        success INTEGER NOT NULL,
+        status_code INTEGER,
+        failure_kind TEXT,
+        failure_message TEXT,
+        response_time_ms INTEGER NOT NULL,
+        dns_ms INTEGER,
+        connect_ms INTEGER,
+        tls_ms INTEGER,
+        ttfb_ms INTEGER,
+        download_ms INTEGER,
+        attempts INTEGER NOT NULL,
+        result TEXT NOT NULL
+    );
+    CREATE INDEX checks_by_url ON checks (url, checked_at);",
+];
+
+/// A SQLite database holding every check result ever recorded in it.
+pub struct History {
+    conn: Connection,
+}
+
+/// Availability of one URL over a window of time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Uptime {
+    pub total: u64,
+    pub succeeded: u64,
+    /// Mean response time of the successful checks.
+    pub average_response_time_ms: Option<f64>,
+    /// When the most recent failed check in the window completed.
+    pub last_failure: Option<DateTime<Local>>,
+}
+
+impl Uptime {
+    /// Share of checks that succeeded, from 0 to 100, or `None` if the URL
+    /// was not checked in the window.
+    pub fn percent(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.succeeded as f64 * 100.0 / self.total as f64)
+    }
+}
+
+impl History {
+    /// Opens (creating if needed) the database at `path` and brings its
+    /// schema up to date.
+    pub fn open(path: impl AsRef<Path>) -> Result<History, String> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .map_err(|err| format!("cannot open {}: {}", path.display(), err))?;
+        let mut history = History { conn };
+        history
+            .migrate()
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        Ok(history)
+    }
+
+    /// Applies every migration the database has not seen yet.
+    fn migrate(&mut self) -> Result<(), String> {
+        let version: usize = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|err| err.to_string())?;
+        if version > MIGRATIONS.len() {
+            return Err(format!(
+                "database schema version {} is newer than this program supports ({})",
+                version,
+                MIGRATIONS.len()
+            ));
+        }
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self.conn.transaction().map_err(|err| err.to_string())?;
+            tx.execute_batch(migration)
+                .and_then(|_| tx.pragma_update(None, "user_version", index + 1))
+                .and_then(|_| tx.commit())
+                .map_err(|err| format!("migration {} failed: {}", index + 1, err))?;
+        }
+        Ok(())
+    }
+
+    /// How many migrations the database has had applied.
+    pub fn schema_version(&self) -> Result<usize, String> {
+        self.conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|err| err.to_string())
+    }
+
+    /// Appends one check result.
+    pub fn record(&self, status: &WebsiteStatus) -> Result<(), String> {
+        let timings = status.timings();
+        let result = serde_json::to_string(status).map_err(|err| err.to_string())?;
+        self.conn
+            .execute(
+                "INSERT INTO checks (url, name, checked_at, success, status_code,
+                    failure_kind, failure_message, response_time_ms, dns_ms, connect_ms,
+                    tls_ms, ttfb_ms, download_ms, attempts, result)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                params![
+                    status.url(),
+                    status.name(),
+                    status.timestamp().timestamp_millis(),
+                    status.is_success(),
+                    status.status_code(),
+                    status.failure().map(|f| f.kind()),
+                    status.failure().map(|f| f.message()),
+                    status.response_time_ms(),
+                    timings.dns_ms,
+                    timings.connect_ms,
+                    timings.tls_ms,
+                    timings.ttfb_ms,
+                    timings.download_ms,
+                    status.attempts().len(),
+                    result,
+                ],
+            )
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+
+    /// Every result recorded for `url` within the `window` up to now,
+    /// oldest first.
+    pub fn recent(&self, url: &str, window: Duration) -> Result<Vec<WebsiteStatus>, String> {
+        let since = window_start(window);
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT result FROM checks WHERE url = ?1 AND checked_at >= ?2
+                 ORDER BY checked_at, id",
+            )
+            .map_err(|err| err.to_string())?;
+        let rows = stmt
+            .query_map(params![url, since.timestamp_millis()], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(|err| err.to_string())?;
+
+        rows.map(|row| {
+            let json = row.map_err(|err| err.to_string())?;
+            serde_json::from_str(&json).map_err(|err| format!("corrupt history row: {}", err))
+        })
+        .collect()
+    }
+
+    /// Availability of `url` over the `window` up to now.
+    pub fn uptime(&self, url: &str, window: Duration) -> Result<Uptime, String> {
+        let since = window_start(window);
+        let (total, succeeded, average_response_time_ms): (u64, Option<u64>, Option<f64>) = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*), SUM(success),
+                    AVG(CASE WHEN success THEN response_time_ms END)
+                 FROM checks WHERE url = ?1 AND checked_at >= ?2",
+                params![url, since.timestamp_millis()],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(|err| err.to_string())?;
+
+        let last_failure: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT MAX(checked_at) FROM checks
+                 WHERE url = ?1 AND checked_at >= ?2 AND NOT success",
+                params![url, since.timestamp_millis()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|err| err.to_string())?
+            .flatten();
+
+        Ok(Uptime {
+            total,
+            succeeded: succeeded.unwrap_or(0),
+            average_response_time_ms,
+            last_failure: last_failure
+                .and_then(DateTime::from_timestamp_millis)
+                .map(|t| t.with_timezone(&Local)),
+        })
+    }
+}
+
+/// The moment `window` before now, or the Unix epoch for huge windows.
+fn window_start(window: Duration) -> DateTime<Local> {
+    chrono::Duration::from_std(window)
+        .ok()
+        .and_then(|window| Local::now().checked_sub_signed(window))
+        .unwrap_or_else(|| DateTime::UNIX_EPOCH.with_timezone(&Local))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::Failure;
+    use crate::target::Target;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.sqlite", name, std::process::id()))
+    }
+
+    fn status(url: &str, up: bool, age: Duration, response_ms: u64) -> WebsiteStatus {
+        let failure = (!up).then(|| Failure::UnexpectedStatus("expected status 2xx".to_string()));
+        let timestamp = Local::now() - chrono::Duration::from_std(age).unwrap();
+        WebsiteStatus::new(
+            &Target::new(url).with_name("Example"),
+            Some(if up { 200 } else { 503 }),
+            failure,
+            Duration::from_millis(response_ms),
+            timestamp,
+        )
+    }
+
+    #[test]
+    fn test_migrations_run_once() {
+        let path = temp_path("history-migrations");
+        let history = History::open(&path).unwrap();
+        assert_eq!(history.schema_version(), Ok(MIGRATIONS.len()));
+        history
+            .record(&status("https://a.example", true, Duration::ZERO, 5))
+            .unwrap();
+        drop(history);
+
+        // Reopening keeps the data and does not re-run migrations
+        let history = History::open(&path).unwrap();
+        let recent = history.recent("https://a.example", Duration::from_secs(60));
+        assert_eq!(recent.unwrap().len(), 1);
+        drop(history);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_recent_returns_results_in_window_oldest_first() {
+        let history = History::open(":memory:").unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let old = status("https://a.example", true, day * 2, 5);
+        let older = status("https://a.example", false, Duration::from_secs(120), 7);
+        let newer = status("https://a.example", true, Duration::from_secs(60), 9);
+        for s in [&old, &newer, &older] {
+            history.record(s).unwrap();
+        }
+        history
+            .record(&status("https://b.example", true, Duration::ZERO, 1))
+            .unwrap();
+
+        let results = history.recent("https://a.example", day).unwrap();
+        assert_eq!(results, vec![older, newer]);
+    }
+
+    #[test]
+    fn test_uptime_over_window() {
+        let history = History::open(":memory:").unwrap();
+        let hour = Duration::from_secs(60 * 60);
+        history
+            .record(&status("https://a.example", true, hour / 2, 10))
+            .unwrap();
+        history
+            .record(&status("https://a.example", true, hour / 4, 30))
+            .unwrap();
+        history
+            .record(&status("https://a.example", false, hour / 8, 500))
+            .unwrap();
+        history
+            .record(&status("https://a.example", false, hour * 2, 500))
+            .unwrap();
+
+        let uptime = history.uptime("https://a.example", hour).unwrap();
+        assert_eq!(uptime.total, 3);
+        assert_eq!(uptime.succeeded, 2);
+        assert_eq!(uptime.average_response_time_ms, Some(20.0));
+        assert!(uptime.last_failure.is_some());
+        assert!((uptime.percent().unwrap() - 66.666).abs() < 0.01);
+
+        let unknown = history.uptime("https://b.example", hour).unwrap();
+        assert_eq!(unknown.total, 0);
+        assert_eq!(unknown.percent(), None);
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::metrics::BUCKETS;
+use crate::report::RunReport;
+use crate::status::WebsiteStatus;
+
+/// Size of the latency histogram, in SVG user units.
+const BAR_WIDTH: usize = 56;
+const CHART_HEIGHT: usize = 140;
+
+const STYLE: &str = r##"
+  body { font-family: system-ui, sans-serif; margin: 2rem; color: #222; }
+  h1 { font-size: 1.5rem; margin-bottom: 0.2rem; }
+  h2 { font-size: 1.15rem; margin-top: 2rem; }
+  h3 { font-size: 1rem; margin-bottom: 0.3rem; }
+  .meta { color: #666; }
+  .cards { display: flex; flex-wrap: wrap; gap: 0.75rem; }
+  .card { border: 1px solid #ddd; border-radius: 6px; padding: 0.6rem 1rem; min-width: 6rem; }
+  .card .value { font-size: 1.4rem; font-weight: 600; }
+  .card .label { color: #666; font-size: 0.8rem; }
+  table { border-collapse: collapse; width: 100%; }
+  th, td { text-align: left; padding: 0.4rem 0.6rem; border-bottom: 1px solid #e5e5e5; vertical-align: top; }
+  th { cursor: pointer; user-select: none; font-size: 0.8rem; text-transform: uppercase; color: #555; }
+  th.asc::after { content: " \25B2"; }
+  th.desc::after { content: " \25BC"; }
+  td.number { text-align: right; font-variant-numeric: tabular-nums; }
+  .badge { display: inline-block; min-width: 4.5rem; text-align: center; padding: 0.1rem 0.5rem;
+           border-radius: 1rem; color: #fff; font-size: 0.8rem; font-weight: 600; }
+  .up { background: #2e9d4f; }
+  .warning { background: #d99a00; }
+  .down { background: #d33c3c; }
+  .url { color: #666; font-size: 0.85rem; }
+  .error { color: #b02a2a; }
+  svg text { font-size: 11px; fill: #444; }
+  svg rect { fill: #4a7fc1; }
+"##;
+
+/// Sorts the results table by the clicked column, using each cell's
+/// `data-sort` value and comparing numbers numerically.
+const SCRIPT: &str = r##"
+  document.querySelectorAll("#results th").forEach((th, column) => {
+    th.addEventListener("click", () => {
+      const ascending = !th.classList.contains("asc");
+      document.querySelectorAll("#results th").forEach(h => h.classList.remove("asc", "desc"));
+      th.classList.add(ascending ? "asc" : "desc");
+      const body = document.querySelector("#results tbody");
+      const key = row => row.cells[column].dataset.sort;
+      const rows = Array.from(body.rows).sort((a, b) => {
+        const [x, y] = [key(a), key(b)];
+        const order = x !== "" && y !== "" && !isNaN(x) && !isNaN(y)
+          ? Number(x) - Number(y)
+          : x.localeCompare(y);
+        return ascending ? order : -order;
+      });
+      body.append(...rows);
+    });
+  });
+"##;
+
+/// Renders `report` as a standalone HTML page.
+pub(crate) fn render(report: &RunReport) -> String {
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Website status report</title>\n<style>{}</style>\n</head>\n<body>\n",
+        STYLE
+    );
+    let _ = writeln!(out, "<h1>Website status report</h1>");
+    let _ = writeln!(
+        out,
+        "<p class=\"meta\">Run from {} to {} by {}</p>",
+        report.started_at.format("%Y-%m-%d %H:%M:%S %:z"),
+        report.finished_at.format("%Y-%m-%d %H:%M:%S %:z"),
+        escape(&report.generator)
+    );
+
+    summary(&mut out, report);
+    let _ = writeln!(out, "<h2>Response times</h2>");
+    histogram(&mut out, &report.results);
+    let _ = writeln!(out, "<h2>Failures</h2>");
+    failures(&mut out, &report.results);
+    let _ = writeln!(out, "<h2>Results</h2>");
+    table(&mut out, &report.results);
+
+    let _ = write!(out, "<script>{}</script>\n</body>\n</html>\n", SCRIPT);
+    out
+}
+
+/// The result counts and the response time statistics that the command
+/// line prints at the end of a run.
+fn summary(out: &mut String, report: &RunReport) {
+    let mut cards = vec![
+        ("Targets", report.summary.total.to_string()),
+        ("Up", report.summary.succeeded.to_string()),
+        ("Down", report.summary.failed.to_string()),
+    ];
+    if let Some(times) = report.response_times() {
+        cards.push(("Min", format!("{} ms", times.min_ms)));
+        cards.push(("Max", format!("{} ms", times.max_ms)));
+        cards.push(("Avg", format!("{:.2} ms", times.avg_ms)));
+    }
+    let _ = writeln!(out, "<div class=\"cards\">");
+    for (label, value) in cards {
+        let _ = writeln!(
+            out,
+            "<div class=\"card\"><div class=\"value\">{}</div><div class=\"label\">{}</div></div>",
+            value, label
+        );
+    }
+    let _ = writeln!(out, "</div>");
+    if report.summary.succeeded == 0 {
+        let _ = writeln!(out, "<p>No successful responses to summarize.</p>");
+    }
+}
+
+/// Counts of results per response time bucket, the last counting those
+/// slower than every bound.
+fn bucket_counts(results: &[WebsiteStatus]) -> Vec<usize> {
+    let mut counts = vec![0; BUCKETS.len() + 1];
+    for status in results {
+        let seconds = status.response_time().as_secs_f64();
+        let bucket = BUCKETS
+            .iter()
+            .position(|&bound| seconds <= bound)
+            .unwrap_or(BUCKETS.len());
+        counts[bucket] += 1;
+    }
+    counts
+}
+
+/// Draws the response times of every result as an inline SVG bar chart.
+fn histogram(out: &mut String, results: &[WebsiteStatus]) {
+    let counts = bucket_counts(results);
+    let labels = BUCKETS
+        .iter()
+        .map(|&bound| match bound {
+            b if b < 1.0 => format!("≤{} ms", (b * 1000.0).round()),
+            b => format!("≤{} s", b),
+        })
+        .chain([format!("&gt;{} s", BUCKETS[BUCKETS.len() - 1])]);
+    let highest = counts.iter().copied().max().unwrap_or(0).max(1);
+
+    let width = counts.len() * BAR_WIDTH;
+    let _ = writeln!(
+        out,
+        "<svg width=\"{}\" height=\"{}\" role=\"img\" aria-label=\"Response time histogram\">",
+        width,
+        CHART_HEIGHT + 40
+    );
+    for (index, (count, label)) in counts.iter().zip(labels).enumerate() {
+        let height = count * CHART_HEIGHT / highest;
+        let x = index * BAR_WIDTH;
+        let center = x + BAR_WIDTH / 2;
+        let top = CHART_HEIGHT + 20 - height;
+        let _ = writeln!(
+            out,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"><title>{}: {}</title></rect>",
+            x + 4,
+            top,
+            BAR_WIDTH - 8,
+            height,
+            label,
+            count
+        );
+        let _ = writeln!(
+            out,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            center,
+            top - 4,
+            count
+        );
+        let _ = writeln!(
+            out,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            center,
+            CHART_HEIGHT + 34,
+            label
+        );
+    }
+    let _ = writeln!(out, "</svg>");
+}
+
+/// Lists failed results under their failure kind.
+fn failures(out: &mut String, results: &[WebsiteStatus]) {
+    let mut groups: BTreeMap<&str, Vec<&WebsiteStatus>> = BTreeMap::new();
+    for status in results {
+        if let Some(failure) = status.failure() {
+            groups.entry(failure.kind()).or_default().push(status);
+        }
+    }
+    if groups.is_empty() {
+        let _ = writeln!(out, "<p>No failures.</p>");
+        return;
+    }
+    for (kind, statuses) in groups {
+        let _ = writeln!(out, "<h3>{} ({})</h3>\n<ul>", kind, statuses.len());
+        for status in statuses {
+            let _ = writeln!(
+                out,
+                "<li><strong>{}</strong>: <span class=\"error\">{}</span></li>",
+                escape(status.label()),
+                escape(
+                    &status
+                        .failure()
+                        .map(ToString::to_string)
+                        .unwrap_or_default()
+                )
+            );
+        }
+        let _ = writeln!(out, "</ul>");
+    }
+}
+
+/// Writes every result as a row of the sortable table.
+fn table(out: &mut String, results: &[WebsiteStatus]) {
+    let _ = writeln!(
+        out,
+        "<table id=\"results\">\n<thead><tr><th>State</th><th>Target</th><th>Status</th>\
+         <th>Response time</th><th>Tags</th><th>Checked</th><th>Problem</th></tr></thead>\n<tbody>"
+    );
+    for status in results {
+        let state = match status.failure() {
+            Some(_) => "down",
+            None if status.warning().is_some() => "warning",
+            None => "up",
+        };
+        let target = match status.name() {
+            Some(name) => format!(
+                "{}<div class=\"url\">{}</div>",
+                escape(name),
+                escape(status.url())
+            ),
+            None => escape(status.url()),
+        };
+        let code = status.status_code().map(|c| c.to_string());
+        let problem = match status.failure() {
+            Some(failure) => failure.to_string(),
+            None => status.warning().unwrap_or_default().to_string(),
+        };
+        let tags = status.tags().join(", ");
+        let timestamp = status.timestamp();
+
+        let _ = writeln!(
+            out,
+            "<tr><td data-sort=\"{state}\"><span class=\"badge {state}\">{state}</span></td>\
+             <td data-sort=\"{}\">{}</td>\
+             <td class=\"number\" data-sort=\"{}\">{}</td>\
+             <td class=\"number\" data-sort=\"{}\">{} ms</td>\
+             <td data-sort=\"{}\">{}</td>\
+             <td data-sort=\"{}\">{}</td>\
+             <td data-sort=\"{}\" class=\"error\">{}</td></tr>",
+            escape(status.label()),
+            target,
+            code.as_deref().unwrap_or(""),
+            code.as_deref().unwrap_or("—"),
+            status.response_time_ms(),
+            status.response_time_ms(),
+            escape(&tags),
+            escape(&tags),
+            timestamp.timestamp_millis(),
+            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            escape(&problem),
+            escape(&problem),
+        );
+    }
+    let _ = writeln!(out, "</tbody>\n</table>");
+}
+
+/// Escapes text for use in HTML content and quoted attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checker::Checker;
+    use crate::status::Failure;
+    use crate::target::Target;
+    use chrono::Local;
+    use std::time::Duration;
+
+    fn status(url: &str, failure: Option<Failure>, ms: u64) -> WebsiteStatus {
+        WebsiteStatus::new(
+            &Target::new(url),
+            Some(200),
+            failure,
+            Duration::from_millis(ms),
+            Local::now(),
+        )
+    }
+
+    #[test]
+    fn test_report_groups_failures_and_escapes_text() {
+        let results = vec![
+            status("https://a.example/?q=<script>", None, 40),
+            status(
+                "https://b.example",
+                Some(Failure::Assertion("body contains \"<b>Error</b>\"".into())),
+                120,
+            ),
+            status(
+                "https://c.example",
+                Some(Failure::Request("connection refused".into())),
+                3,
+            ),
+            status(
+                "https://d.example",
+                Some(Failure::Request("timed out".into())),
+                3,
+            ),
+        ];
+        let report = RunReport::new(&Checker::default(), Local::now(), results);
+        let html = render(&report);
+
+        assert!(html.contains("https://a.example/?q=&lt;script&gt;"));
+        assert!(!html.contains("q=<script>"));
+        assert!(html.contains("&lt;b&gt;Error&lt;/b&gt;"));
+        assert!(html.contains("<h3>assertion (1)</h3>"));
+        assert!(html.contains("<h3>request (2)</h3>"));
+        // Groups are listed in a stable order
+        assert!(html.find("assertion (1)") < html.find("request (2)"));
+        assert!(html.contains("<div class=\"value\">40 ms</div><div class=\"label\">Min</div>"));
+        // Nothing is loaded from elsewhere
+        assert!(!html.contains(" src=") && !html.contains("<link"));
+    }
+
+    #[test]
+    fn test_histogram_buckets_every_result() {
+        let results = [
+            status("https://a.example", None, 4),
+            status("https://b.example", None, 7),
+            status("https://c.example", None, 7),
+            status("https://d.example", None, 60_000),
+        ];
+        let counts = bucket_counts(&results);
+        assert_eq!(counts.len(), BUCKETS.len() + 1);
+        assert_eq!(counts[0], 1); // ≤5 ms
+        assert_eq!(counts[1], 2); // ≤10 ms
+        assert_eq!(counts[BUCKETS.len()], 1); // slower than 10 s
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,520 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use hyper::body::HttpBody;
+use hyper::{Body, Request, Response, Uri};
+use reqwest::header::{
+    ACCEPT, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, HOST, HeaderMap, HeaderValue,
+    LOCATION, PROXY_AUTHORIZATION, RETRY_AFTER, WWW_AUTHENTICATE,
+};
+use reqwest::{Method, Url};
+use tokio::time;
+
+use crate::certificate::CertificateInfo;
+use crate::check::{BoxFuture, Check};
+use crate::connector::{ConnectError, ConnectionInfo, TimedConnector};
+use crate::expected::ExpectedStatus;
+use crate::redirect::{Hop, RedirectPolicy};
+use crate::retry::{Attempt, RetryPolicy};
+use crate::status::{Failure, WebsiteStatus};
+use crate::target::Target;
+use crate::timing::{Timings, millis};
+
+/// The connection-pooled client HTTP checks share, whose connections
+/// record how they were opened.
+pub(crate) type HttpClient = hyper::Client<TimedConnector, Body>;
+
+/// A client trusting the system's roots and `root_certificates`.
+pub(crate) fn http_client(
+    root_certificates: &[native_tls::Certificate],
+) -> native_tls::Result<HttpClient> {
+    Ok(hyper::Client::builder().build(TimedConnector::new(root_certificates)?))
+}
+
+/// Settings shared by every check a [`Checker`](crate::Checker) makes.
+#[derive(Debug, Clone)]
+pub(crate) struct CheckSettings {
+    pub method: Method,
+    pub headers: HeaderMap,
+    pub timeout: Duration,
+    pub retry: RetryPolicy,
+    pub max_body_bytes: usize,
+    pub expected_status: ExpectedStatus,
+    pub cert_warning_days: u32,
+    pub redirects: RedirectPolicy,
+}
+
+/// Checks `http://` and `https://` targets with a shared client.
+pub(crate) struct HttpCheck {
+    client: HttpClient,
+    settings: Arc<CheckSettings>,
+}
+
+impl HttpCheck {
+    pub fn new(client: HttpClient, settings: Arc<CheckSettings>) -> Self {
+        HttpCheck { client, settings }
+    }
+}
+
+impl Check for HttpCheck {
+    fn check<'a>(&'a self, target: &'a Target) -> BoxFuture<'a, WebsiteStatus> {
+        Box::pin(check_target(&self.client, target, &self.settings))
+    }
+}
+
+/// Checks a single target: requests it, then judges the response.
+pub(crate) async fn check_target(
+    client: &HttpClient,
+    target: &Target,
+    settings: &CheckSettings,
+) -> WebsiteStatus {
+    let start = Instant::now();
+
+    let mut method = target.method().unwrap_or(&settings.method).clone();
+    let timeout = target.timeout().unwrap_or(settings.timeout);
+    let retry = match target.retries() {
+        Some(retries) => settings.retry.clone().with_retries(retries),
+        None => settings.retry.clone(),
+    };
+    let policy = target.redirects().unwrap_or(&settings.redirects);
+
+    // Target headers replace checker-wide headers of the same name
+    let mut headers = settings.headers.clone();
+    for name in target.headers().keys() {
+        headers.remove(name);
+    }
+    headers.extend(target.headers().clone());
+    if !headers.contains_key(ACCEPT) {
+        headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+    }
+
+    // Timings and the certificate describe the connection of the last hop
+    let mut timings = Timings::default();
+    let mut certificate: Option<CertificateInfo> = None;
+    let mut url = target.url().to_string();
+    let mut body = target.body().map(<[u8]>::to_vec);
+    let mut attempts = Vec::new();
+    let mut chain = Vec::new();
+    let (status_code, failure) = loop {
+        let (current, uri) = match parse_url(&url) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                chain.push(Hop {
+                    url,
+                    status_code: None,
+                    duration_ms: 0,
+                });
+                break (
+                    None,
+                    Some(Failure::Request(format!("invalid URL: {}", err))),
+                );
+            }
+        };
+        let request = || {
+            let body = body.clone().map_or_else(Body::empty, Body::from);
+            let mut request = Request::new(body);
+            *request.method_mut() = method.clone();
+            *request.uri_mut() = uri.clone();
+            *request.headers_mut() = headers.clone();
+            request
+        };
+        let sent = send(client, request, timeout, &retry).await;
+        attempts.extend(sent.attempts);
+        let duration_ms = millis(sent.received_at - sent.sent_at);
+
+        let response = match sent.response {
+            Ok(response) => response,
+            Err(err) => {
+                let connect = err.connect_error();
+                timings = connect.map(|c| c.setup.clone()).unwrap_or_default();
+                certificate = connect.and_then(|c| c.certificate.clone());
+                chain.push(Hop {
+                    url,
+                    status_code: None,
+                    duration_ms,
+                });
+                let host = uri.host().unwrap_or_default();
+                break (
+                    None,
+                    Some(request_failure(&err, host, certificate.as_ref())),
+                );
+            }
+        };
+        let code = response.status().as_u16();
+        chain.push(Hop {
+            url: current.to_string(),
+            status_code: Some(code),
+            duration_ms,
+        });
+
+        let connection = response.extensions().get::<ConnectionInfo>();
+        timings = connection
+            .map(|c| c.timings(sent.sent_at, sent.received_at))
+            .unwrap_or_default();
+        certificate = connection.and_then(|c| c.certificate.clone());
+
+        let next = match next_hop(&response, &current, policy, &chain) {
+            Ok(Some(next)) => next,
+            Ok(None) => {
+                let failure =
+                    match judge_response(response, target, settings, sent.deadline, &mut timings)
+                        .await
+                    {
+                        Some(failure) => Some(failure),
+                        None => check_final_url(target, &chain),
+                    };
+                break (Some(code), failure);
+            }
+            Err(failure) => break (Some(code), Some(failure)),
+        };
+
+        // Like browsers, turn the request into a GET where the status
+        // allows it, and keep credentials from leaking to other hosts
+        let to_get = code == 303 || (matches!(code, 301 | 302) && method == Method::POST);
+        if to_get && method != Method::HEAD {
+            method = Method::GET;
+            body = None;
+            headers.remove(CONTENT_TYPE);
+            headers.remove(CONTENT_LENGTH);
+        }
+        if next.host_str() != current.host_str() {
+            for name in [
+                AUTHORIZATION,
+                COOKIE,
+                PROXY_AUTHORIZATION,
+                WWW_AUTHENTICATE,
+                HOST,
+            ] {
+                headers.remove(name);
+            }
+        }
+        url = next.to_string();
+    };
+
+    let now = chrono::Local::now();
+    let warning = certificate
+        .as_ref()
+        .and_then(|cert| cert.expiry_warning(settings.cert_warning_days, now));
+    WebsiteStatus::new(target, status_code, failure, start.elapsed(), now)
+        .with_timings(timings, attempts)
+        .with_redirect_chain(chain)
+        .with_certificate(certificate, warning)
+}
+
+/// Parses `url`, returning it with the URI a request for it is sent to,
+/// which drops the fragment.
+fn parse_url(url: &str) -> Result<(Url, Uri), String> {
+    let url = Url::parse(url).map_err(|err| err.to_string())?;
+    let mut without_fragment = url.clone();
+    without_fragment.set_fragment(None);
+    let uri = without_fragment
+        .as_str()
+        .parse()
+        .map_err(|err: hyper::http::uri::InvalidUri| err.to_string())?;
+    Ok((url, uri))
+}
+
+/// Where `response`, received from `url`, redirects to, if `policy`
+/// follows it. `chain` holds every hop so far, including this response.
+///
+/// Responses that are not redirects, or lack a usable `Location`, are
+/// final and `Ok(None)`.
+fn next_hop(
+    response: &Response<Body>,
+    url: &Url,
+    policy: &RedirectPolicy,
+    chain: &[Hop],
+) -> Result<Option<Url>, Failure> {
+    let code = response.status().as_u16();
+    if !policy.follows() || !matches!(code, 301 | 302 | 303 | 307 | 308) {
+        return Ok(None);
+    }
+    let Some(location) = response
+        .headers()
+        .get(LOCATION)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Ok(None);
+    };
+
+    let next = url
+        .join(location)
+        .map_err(|_| Failure::Redirect(format!("invalid redirect location '{}'", location)))?;
+    if chain.len() > policy.max_hops() {
+        return Err(Failure::Redirect(format!(
+            "more than {} redirects, stopped at {}",
+            policy.max_hops(),
+            url
+        )));
+    }
+    if policy.same_host() && next.host_str() != url.host_str() {
+        return Err(Failure::Redirect(format!(
+            "redirect from {} to another host: {}",
+            url, next
+        )));
+    }
+    if chain.iter().any(|hop| hop.url == next.as_str()) {
+        return Err(Failure::Redirect(format!("redirect loop back to {}", next)));
+    }
+    Ok(Some(next))
+}
+
+/// Verifies the redirects ended at the URL the target expects.
+fn check_final_url(target: &Target, chain: &[Hop]) -> Option<Failure> {
+    let expected = target.final_url()?;
+    let actual = chain.last().map(|hop| hop.url.as_str())?;
+    // Compare normalized URLs, so `https://example.com` matches its `/`
+    let matches = match Url::parse(expected) {
+        Ok(url) => url.as_str() == actual,
+        Err(_) => expected == actual,
+    };
+    if matches {
+        None
+    } else {
+        Some(Failure::Assertion(format!(
+            "final URL is {}, expected {}",
+            actual, expected
+        )))
+    }
+}
+
+/// Describes a failed request to `host`, naming the certificate problem
+/// when the connection failed because the client rejected the certificate.
+fn request_failure(
+    err: &RequestError,
+    host: &str,
+    certificate: Option<&CertificateInfo>,
+) -> Failure {
+    let problem = certificate.and_then(|cert| cert.problem(host, chrono::Local::now()));
+    match problem {
+        Some(problem) => Failure::Certificate(problem),
+        None => Failure::Request(err.to_string()),
+    }
+}
+
+/// Downloads at most `max_body_bytes` of the body, then verifies the status
+/// code is an expected one and evaluates the body assertions.
+///
+/// The body is only kept in memory when the target has assertions.
+async fn judge_response(
+    response: Response<Body>,
+    target: &Target,
+    settings: &CheckSettings,
+    deadline: Instant,
+    timings: &mut Timings,
+) -> Option<Failure> {
+    let code = response.status().as_u16();
+
+    let start = Instant::now();
+    let body = response.into_body();
+    let body = if target.assertions().is_empty() {
+        discard_body(body, settings.max_body_bytes, deadline)
+            .await
+            .map(|_| None)
+    } else {
+        read_body(body, settings.max_body_bytes, deadline)
+            .await
+            .map(Some)
+    };
+    timings.download_ms = Some(millis(start.elapsed()));
+
+    let expected = target
+        .expected_status()
+        .unwrap_or(&settings.expected_status);
+    if !expected.matches(code) {
+        return Some(Failure::UnexpectedStatus(format!(
+            "expected status {}",
+            expected
+        )));
+    }
+
+    match body {
+        Ok(Some(body)) => check_assertions(target, &body),
+        Ok(None) => None,
+        Err(err) => Some(Failure::Request(format!("error reading body: {}", err))),
+    }
+}
+
+/// The result of sending a request, possibly several times.
+struct Sent {
+    response: Result<Response<Body>, RequestError>,
+    attempts: Vec<Attempt>, // Every attempt, including the final one
+    sent_at: Instant,       // When the final attempt was sent
+    received_at: Instant,   // When its response head arrived, or it failed
+    deadline: Instant,      // When the final attempt times out, body included
+}
+
+/// Why a request got no response.
+#[derive(Debug)]
+enum RequestError {
+    Http(hyper::Error),
+    TimedOut(Duration),
+}
+
+impl RequestError {
+    /// What went wrong opening the connection, if that is where it failed.
+    fn connect_error(&self) -> Option<&ConnectError> {
+        match self {
+            RequestError::Http(err) => ConnectError::find(err),
+            RequestError::TimedOut(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::Http(err) => write!(f, "{}", err),
+            RequestError::TimedOut(timeout) => {
+                write!(f, "timed out after {} ms", timeout.as_millis())
+            }
+        }
+    }
+}
+
+/// Sends the request built by `request`, retrying on transport errors and
+/// retryable status codes as `retry` allows. Each attempt has `timeout`
+/// to get its response head and read the body.
+async fn send(
+    client: &HttpClient,
+    request: impl Fn() -> Request<Body>,
+    timeout: Duration,
+    retry: &RetryPolicy,
+) -> Sent {
+    let mut attempts = Vec::new();
+
+    loop {
+        let start = Instant::now();
+        let response = match time::timeout(timeout, client.request(request())).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(err)) => Err(RequestError::Http(err)),
+            Err(_) => Err(RequestError::TimedOut(timeout)),
+        };
+        let received_at = Instant::now();
+        let elapsed = received_at - start;
+
+        let retries_left = (attempts.len() as u32) < retry.retries();
+        let (attempt, retry_after) = match &response {
+            Ok(resp) => {
+                let code = resp.status().as_u16();
+                let retry_after = resp
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string);
+                (Attempt::response(code, elapsed), retry_after)
+            }
+            Err(err) => (Attempt::error(err.to_string(), elapsed), None),
+        };
+        let retryable = match &response {
+            Ok(resp) => retry.should_retry_status(resp.status().as_u16()),
+            Err(_) => true,
+        };
+
+        if !retryable || !retries_left {
+            attempts.push(attempt);
+            return Sent {
+                response,
+                attempts,
+                sent_at: start,
+                received_at,
+                deadline: start + timeout,
+            };
+        }
+
+        let delay = retry.delay(attempts.len() as u32 + 1, retry_after.as_deref());
+        attempts.push(attempt.with_retry_delay(delay));
+        // Release the connection before waiting
+        drop(response);
+        time::sleep(delay).await;
+    }
+}
+
+/// Reads and throws away at most `limit` bytes of the body.
+async fn discard_body(body: Body, limit: usize, deadline: Instant) -> Result<usize, String> {
+    read_capped(body, limit, deadline, |_| {}).await
+}
+
+/// Reads at most `limit` bytes of the body, replacing invalid UTF-8.
+async fn read_body(body: Body, limit: usize, deadline: Instant) -> Result<String, String> {
+    let mut text = Vec::new();
+    read_capped(body, limit, deadline, |chunk| text.extend_from_slice(chunk)).await?;
+    Ok(String::from_utf8_lossy(&text).into_owned())
+}
+
+/// Streams at most `limit` bytes of the body into `sink` until `deadline`,
+/// returning how many bytes were read.
+async fn read_capped(
+    mut body: Body,
+    limit: usize,
+    deadline: Instant,
+    mut sink: impl FnMut(&[u8]),
+) -> Result<usize, String> {
+    let mut read = 0;
+    while read < limit {
+        let chunk = match time::timeout_at(deadline.into(), body.data()).await {
+            Ok(Some(chunk)) => chunk.map_err(|err| err.to_string())?,
+            Ok(None) => break,
+            Err(_) => return Err("timed out".to_string()),
+        };
+        let take = chunk.len().min(limit - read);
+        sink(&chunk[..take]);
+        read += take;
+    }
+    Ok(read)
+}
+
+/// Evaluates every assertion of `target`, combining all failures into one.
+fn check_assertions(target: &Target, body: &str) -> Option<Failure> {
+    let failed: Vec<String> = target
+        .assertions()
+        .iter()
+        .filter_map(|assertion| assertion.check(body).err())
+        .collect();
+    if failed.is_empty() {
+        None
+    } else {
+        Some(Failure::Assertion(failed.join("; ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockServer, Reply};
+
+    #[tokio::test]
+    async fn test_send_retries_retryable_statuses() {
+        let unavailable = Reply::status(503).header("Retry-After", "0");
+        let server =
+            MockServer::start().route("/", [unavailable.clone(), unavailable, Reply::status(200)]);
+        let url = server.url("/");
+
+        let client = http_client(&[]).unwrap();
+        let request = || Request::get(url.as_str()).body(Body::empty()).unwrap();
+        let retry = RetryPolicy::new(3).with_base_delay(Duration::from_secs(60));
+        let sent = send(&client, request, Duration::from_secs(5), &retry).await;
+
+        assert_eq!(sent.response.unwrap().status().as_u16(), 200);
+        let codes: Vec<_> = sent.attempts.iter().map(|a| a.status_code).collect();
+        assert_eq!(codes, [Some(503), Some(503), Some(200)]);
+        // Retry-After: 0 replaced the 60 second backoff
+        assert_eq!(sent.attempts[0].retry_delay_ms, Some(0));
+        assert_eq!(sent.attempts[2].retry_delay_ms, None);
+    }
+
+    #[test]
+    fn test_check_assertions_reports_every_failure() {
+        let target: Target = r#"https://example.com contains "Welcome" not-contains Exception"#
+            .parse()
+            .unwrap();
+        assert_eq!(check_assertions(&target, "<h1>Welcome</h1>"), None);
+        assert_eq!(
+            check_assertions(&target, "Exception: out of memory"),
+            Some(Failure::Assertion(
+                "body does not contain \"Welcome\"; body contains \"Exception\"".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt::Write;
+
+use crate::report::RunReport;
+use crate::status::{Failure, WebsiteStatus};
+
+/// Renders `report` as JUnit XML: one `<testcase>` per result, grouped into
+/// a `<testsuite>` per name returned by `suite_of`, in order of first
+/// appearance.
+pub(crate) fn render(report: &RunReport, suite_of: impl Fn(&WebsiteStatus) -> String) -> String {
+    let mut suites: Vec<(String, Vec<&WebsiteStatus>)> = Vec::new();
+    for status in &report.results {
+        let name = suite_of(status);
+        match suites.iter_mut().find(|(suite, _)| *suite == name) {
+            Some((_, statuses)) => statuses.push(status),
+            None => suites.push((name, vec![status])),
+        }
+    }
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{}\" timestamp=\"{}\">",
+        escape(&report.generator),
+        report.summary.total,
+        report.summary.failed,
+        seconds(report.results.iter()),
+        report.started_at.format("%Y-%m-%dT%H:%M:%S")
+    );
+    for (name, statuses) in &suites {
+        let failed = statuses.iter().filter(|s| !s.is_success()).count();
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" time=\"{}\" timestamp=\"{}\">",
+            escape(name),
+            statuses.len(),
+            failed,
+            seconds(statuses.iter().copied()),
+            report.started_at.format("%Y-%m-%dT%H:%M:%S")
+        );
+        for status in statuses {
+            testcase(&mut out, name, status);
+        }
+        let _ = writeln!(out, "  </testsuite>");
+    }
+    let _ = writeln!(out, "</testsuites>");
+    out
+}
+
+fn testcase(out: &mut String, suite: &str, status: &WebsiteStatus) {
+    let _ = write!(
+        out,
+        "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+        escape(status.label()),
+        escape(suite),
+        seconds([status])
+    );
+    let Some(failure) = status.failure() else {
+        match status.warning() {
+            Some(warning) => {
+                let _ = writeln!(
+                    out,
+                    ">\n      <system-out>{}</system-out>\n    </testcase>",
+                    escape(warning)
+                );
+            }
+            None => {
+                let _ = writeln!(out, "/>");
+            }
+        }
+        return;
+    };
+
+    let message = describe(status, failure);
+    let _ = writeln!(
+        out,
+        ">\n      <failure type=\"{}\" message=\"{}\">{}\nURL: {}\nAttempts: {}</failure>\n    </testcase>",
+        failure.kind(),
+        escape(&message),
+        escape(&message),
+        escape(status.url()),
+        status.attempts().len().max(1)
+    );
+}
+
+/// The failure as CI should show it, naming the status code received for
+/// unexpected statuses.
+fn describe(status: &WebsiteStatus, failure: &Failure) -> String {
+    match (failure, status.status_code()) {
+        (Failure::UnexpectedStatus(message), Some(code)) => format!("HTTP {}, {}", code, message),
+        _ => failure.message().to_string(),
+    }
+}
+
+/// The summed response time of `statuses` in seconds, as JUnit expects.
+fn seconds<'a>(statuses: impl IntoIterator<Item = &'a WebsiteStatus>) -> String {
+    let ms: u64 = statuses.into_iter().map(|s| s.response_time_ms()).sum();
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+/// Escapes text for XML content and attributes, dropping the control
+/// characters XML 1.0 cannot represent.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checker::Checker;
+    use crate::target::Target;
+    use chrono::Local;
+    use std::time::Duration;
+
+    fn status(target: Target, code: Option<u16>, failure: Option<Failure>) -> WebsiteStatus {
+        WebsiteStatus::new(
+            &target,
+            code,
+            failure,
+            Duration::from_millis(1250),
+            Local::now(),
+        )
+    }
+
+    #[test]
+    fn test_results_become_testcases_grouped_into_suites() {
+        let results = vec![
+            status(
+                Target::new("https://a.example").with_tag("critical"),
+                Some(200),
+                None,
+            ),
+            status(
+                Target::new("https://b.example").with_name("B & co"),
+                Some(503),
+                Some(Failure::UnexpectedStatus("expected status 2xx".into())),
+            ),
+            status(
+                Target::new("https://c.example").with_tag("critical"),
+                None,
+                Some(Failure::Request("connection <refused>\u{1b}".into())),
+            ),
+        ];
+        let report = RunReport::new(&Checker::default(), Local::now(), results);
+        let xml = render(&report, |status| {
+            status.tags().first().cloned().unwrap_or("untagged".into())
+        });
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert!(xml.contains("tests=\"3\" failures=\"2\" errors=\"0\" time=\"3.750\""));
+        assert!(xml.contains(
+            "<testsuite name=\"critical\" tests=\"2\" failures=\"1\" errors=\"0\" skipped=\"0\" time=\"2.500\""
+        ));
+        assert!(xml.contains("<testsuite name=\"untagged\" tests=\"1\" failures=\"1\""));
+        assert!(xml.find("name=\"critical\"") < xml.find("name=\"untagged\""));
+        assert!(xml.contains(
+            "<testcase name=\"https://a.example\" classname=\"critical\" time=\"1.250\"/>"
+        ));
+        assert!(xml.contains(
+            "<failure type=\"unexpected_status\" message=\"HTTP 503, expected status 2xx\">"
+        ));
+        assert!(xml.contains("<testcase name=\"B &amp; co\""));
+        assert!(xml.contains("message=\"connection &lt;refused&gt;\""));
+        assert_eq!(xml.matches("<testcase ").count(), 3);
+        assert!(xml.ends_with("  </testsuite>\n</testsuites>\n"));
+    }
+
+    #[test]
+    fn test_warnings_go_to_system_out() {
+        let report = RunReport::new(
+            &Checker::default(),
+            Local::now(),
+            vec![
+                status(Target::new("https://a.example"), Some(200), None)
+                    .with_certificate(None, Some("certificate expires in 3 days".into())),
+            ],
+        );
+        let xml = render(&report, |_| "sites.toml".to_string());
+        assert!(xml.contains("<system-out>certificate expires in 3 days</system-out>"));
+        assert!(!xml.contains("<failure"));
+    }
+}
@@ -0,0 +1,74 @@
+//! Concurrent website availability checking.
+//!
+//! The [`Checker`] checks a list of [`Target`]s concurrently on an async
+//! runtime and produces one [`WebsiteStatus`] per target:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use website_status_checker::Checker;
+//!
+//! let checker = Checker::builder()
+//!     .workers(4)
+//!     .timeout(Duration::from_secs(5))
+//!     .retries(2)
+//!     .build();
+//!
+//! for status in checker.run(["https://www.rust-lang.org", "https://github.com"]) {
+//!     println!("{} -> {:?}", status.url(), status.status_code());
+//! }
+//! ```
+
+mod alert;
+mod assertion;
+mod certificate;
+mod check;
+mod checker;
+mod config;
+mod connector;
+mod dashboard;
+mod dns;
+mod duration;
+mod expected;
+mod history;
+mod html_report;
+mod http;
+mod junit;
+mod limits;
+mod metrics;
+#[cfg(test)]
+mod mock;
+mod outcome;
+mod output;
+mod redirect;
+mod report;
+mod retry;
+mod shutdown;
+mod status;
+mod target;
+mod tcp;
+mod timing;
+
+pub use alert::{Alert, AlertEvent, AlertTracker, Webhook};
+pub use assertion::BodyAssertion;
+pub use certificate::CertificateInfo;
+pub use checker::{Checker, CheckerBuilder, Results};
+pub use config::Config;
+pub use dashboard::{Dashboard, DashboardServer};
+pub use duration::parse_duration;
+pub use expected::ExpectedStatus;
+pub use history::{History, Uptime};
+pub use metrics::{Metrics, MetricsServer};
+pub use outcome::{
+    CRITICAL_TAG, EXIT_ALL_DOWN, EXIT_CONFIG_ERROR, EXIT_OK, EXIT_SOME_DOWN, FailThreshold, Verdict,
+};
+pub use output::{OutputFormat, ResultWriter};
+pub use redirect::{Hop, RedirectPolicy};
+pub use report::{ResponseTimes, RunReport, RunSettings, RunSummary, SCHEMA_VERSION};
+pub use retry::{Attempt, RetryPolicy};
+pub use shutdown::Shutdown;
+pub use status::{Failure, WebsiteStatus};
+pub use target::Target;
+pub use timing::Timings;
+
+/// A root certificate to trust, for [`CheckerBuilder::root_certificate`].
+pub use native_tls::Certificate;
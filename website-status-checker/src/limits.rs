@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::Url;
+use tokio::net::lookup_host;
+use tokio::sync::futures::Notified;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{self, Instant};
+
+/// Budgets that keep a run polite towards each server it checks.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct HostLimits {
+    pub per_host: Option<usize>, // Checks in flight per host name
+    pub per_ip: Option<usize>,   // Checks in flight per resolved IP address
+    pub host_rate: Option<f64>,  // Requests started per second per host name
+}
+
+/// Hands out per-host and per-IP slots and rate-limit tokens for one run.
+pub(crate) struct Limiter {
+    limits: HostLimits,
+    hosts: Mutex<HashMap<String, Arc<HostBudget>>>,
+    ips: Mutex<HashMap<IpAddr, Arc<Semaphore>>>,
+    resolved: Mutex<HashMap<String, Option<IpAddr>>>,
+    released: Arc<Notify>, // Signalled whenever a host slot is returned
+}
+
+/// The budget of a single host.
+struct HostBudget {
+    slots: Option<Arc<Semaphore>>,
+    bucket: Option<Mutex<TokenBucket>>,
+}
+
+/// Slots a check holds while it runs; they are returned when it is dropped.
+pub(crate) struct HostPermit {
+    _host: Option<OwnedSemaphorePermit>,
+    _ip: Option<OwnedSemaphorePermit>,
+    released: Arc<Notify>,
+}
+
+impl Drop for HostPermit {
+    fn drop(&mut self) {
+        self.released.notify_waiters();
+    }
+}
+
+impl Limiter {
+    pub fn new(limits: HostLimits) -> Self {
+        Limiter {
+            limits,
+            hosts: Mutex::new(HashMap::new()),
+            ips: Mutex::new(HashMap::new()),
+            resolved: Mutex::new(HashMap::new()),
+            released: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Resolves once some host slot, or an IP slot taken with
+    /// [`try_acquire`](Limiter::try_acquire), is returned. Must be created (and
+    /// enabled) before a failed [`try_acquire_host`](Limiter::try_acquire_host)
+    /// so that a release in between is not missed.
+    pub fn released(&self) -> Notified<'_> {
+        self.released.notified()
+    }
+
+    /// Takes a slot and a rate-limit token for the host of `url` without
+    /// waiting.
+    ///
+    /// On failure returns when a token will next be available, or `None` if
+    /// the host has no free slot and the caller must wait for a release.
+    /// URLs without a host are never limited.
+    pub fn try_acquire_host(&self, url: &str) -> Result<HostPermit, Option<Instant>> {
+        let Some(host) = host_key(url) else {
+            return Ok(self.permit(None));
+        };
+        let budget = self.budget(&host);
+
+        let slot = match &budget.slots {
+            Some(slots) => match Arc::clone(slots).try_acquire_owned() {
+                Ok(slot) => Some(slot),
+                Err(_) => return Err(None),
+            },
+            None => None,
+        };
+        if let Some(bucket) = &budget.bucket {
+            // Returns the slot again if the host is over its rate
+            bucket
+                .lock()
+                .unwrap()
+                .try_take(Instant::now())
+                .map_err(Some)?;
+        }
+        Ok(self.permit(slot))
+    }
+
+    /// Takes a slot on the IP address of the host of `url` as well as the
+    /// host's slot and rate-limit token, without waiting.
+    ///
+    /// The IP slot is taken first, so a host whose IP is saturated does not
+    /// use up its own budget. Addresses come from
+    /// [`resolve_all`](Limiter::resolve_all); a host that was not resolved
+    /// is not limited by IP. Fails like
+    /// [`try_acquire_host`](Limiter::try_acquire_host).
+    pub fn try_acquire(&self, url: &str) -> Result<HostPermit, Option<Instant>> {
+        let ip = match (self.limits.per_ip, self.resolved_ip(url)) {
+            (Some(per_ip), Some(ip)) => match self.ip_slots(ip, per_ip).try_acquire_owned() {
+                Ok(slot) => Some(slot),
+                Err(_) => return Err(None),
+            },
+            _ => None,
+        };
+        let mut permit = self.try_acquire_host(url)?;
+        permit._ip = ip;
+        Ok(permit)
+    }
+
+    /// Looks up the hosts of `urls` concurrently, so that
+    /// [`try_acquire`](Limiter::try_acquire) can take IP slots without
+    /// waiting on DNS. Does nothing without a per-IP limit.
+    pub async fn resolve_all(self: &Arc<Self>, urls: impl IntoIterator<Item = String>) {
+        if self.limits.per_ip.is_none() {
+            return;
+        }
+        let mut lookups = JoinSet::new();
+        for url in urls {
+            let limiter = Arc::clone(self);
+            lookups.spawn(async move {
+                limiter.resolve(&url).await;
+            });
+        }
+        while lookups.join_next().await.is_some() {}
+    }
+
+    /// Waits for a slot on the IP address of the host of `url`, then for the
+    /// host's slot and rate-limit token, taking them together like
+    /// [`try_acquire`](Limiter::try_acquire) so that nothing is held while
+    /// waiting.
+    pub async fn acquire(&self, url: &str) -> HostPermit {
+        if self.limits.per_ip.is_some() {
+            self.resolve(url).await;
+        }
+        loop {
+            let released = self.released();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            match self.try_acquire(url) {
+                Ok(permit) => return permit,
+                Err(Some(ready)) => time::sleep_until(ready).await,
+                Err(None) => released.await,
+            }
+        }
+    }
+
+    fn ip_slots(&self, ip: IpAddr, per_ip: usize) -> Arc<Semaphore> {
+        let mut ips = self.ips.lock().unwrap();
+        Arc::clone(
+            ips.entry(ip)
+                .or_insert_with(|| Arc::new(Semaphore::new(per_ip))),
+        )
+    }
+
+    fn permit(&self, slot: Option<OwnedSemaphorePermit>) -> HostPermit {
+        HostPermit {
+            _host: slot,
+            _ip: None,
+            released: Arc::clone(&self.released),
+        }
+    }
+
+    /// The address the host of `url` was resolved to, if it has been.
+    fn resolved_ip(&self, url: &str) -> Option<IpAddr> {
+        let host = host_key(url)?;
+        self.resolved.lock().unwrap().get(&host).copied().flatten()
+    }
+
+    fn budget(&self, host: &str) -> Arc<HostBudget> {
+        let mut hosts = self.hosts.lock().unwrap();
+        let budget = hosts.entry(host.to_string()).or_insert_with(|| {
+            Arc::new(HostBudget {
+                slots: self.limits.per_host.map(|n| Arc::new(Semaphore::new(n))),
+                bucket: self
+                    .limits
+                    .host_rate
+                    .map(|rate| Mutex::new(TokenBucket::new(rate, Instant::now()))),
+            })
+        });
+        Arc::clone(budget)
+    }
+
+    /// The first address the host of `url` resolves to, looked up once per
+    /// host and run.
+    async fn resolve(&self, url: &str) -> Option<IpAddr> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        if let Some(ip) = self.resolved.lock().unwrap().get(&host) {
+            return *ip;
+        }
+
+        let ip = match url.host()? {
+            url::Host::Ipv4(ip) => Some(IpAddr::V4(ip)),
+            url::Host::Ipv6(ip) => Some(IpAddr::V6(ip)),
+            url::Host::Domain(domain) => {
+                let port = url.port_or_known_default().unwrap_or(0);
+                lookup_host((domain, port))
+                    .await
+                    .ok()
+                    .and_then(|mut addrs| addrs.next())
+                    .map(|addr| addr.ip())
+            }
+        };
+        self.resolved.lock().unwrap().insert(host, ip);
+        ip
+    }
+}
+
+/// The lower-cased host name of `url`, which per-host limits are keyed by.
+pub(crate) fn host_key(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(str::to_ascii_lowercase)
+}
+
+/// A token bucket holding at most one token, refilled at `rate` tokens per
+/// second, so requests to a host are spaced at least `1 / rate` apart.
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    const CAPACITY: f64 = 1.0;
+
+    fn new(rate: f64, now: Instant) -> Self {
+        TokenBucket {
+            rate,
+            tokens: Self::CAPACITY,
+            updated: now,
+        }
+    }
+
+    /// Takes a token, or returns when the next one will be available.
+    fn try_take(&mut self, now: Instant) -> Result<(), Instant> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(Self::CAPACITY);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - self.tokens) / self.rate;
+            Err(now + Duration::from_secs_f64(wait))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_spaces_requests() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(4.0, start);
+
+        assert_eq!(bucket.try_take(start), Ok(()));
+        assert_eq!(
+            bucket.try_take(start),
+            Err(start + Duration::from_millis(250))
+        );
+        assert_eq!(bucket.try_take(start + Duration::from_millis(250)), Ok(()));
+        // An idle host does not build up a burst
+        assert_eq!(bucket.try_take(start + Duration::from_secs(10)), Ok(()));
+        assert!(bucket.try_take(start + Duration::from_secs(10)).is_err());
+    }
+
+    #[test]
+    fn test_host_slots_are_per_host() {
+        let limiter = Limiter::new(HostLimits {
+            per_host: Some(1),
+            ..HostLimits::default()
+        });
+
+        let first = limiter.try_acquire_host("https://a.example/1").unwrap();
+        assert!(matches!(
+            limiter.try_acquire_host("https://A.example/2"),
+            Err(None)
+        ));
+        assert!(limiter.try_acquire_host("https://b.example/").is_ok());
+        assert!(limiter.try_acquire_host("not a url").is_ok());
+
+        drop(first);
+        assert!(limiter.try_acquire_host("https://a.example/2").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_ip_slots_are_shared_between_hosts() {
+        let limiter = Limiter::new(HostLimits {
+            per_ip: Some(1),
+            ..HostLimits::default()
+        });
+
+        let held = limiter.acquire("http://127.0.0.1:1/").await;
+        let waiting = time::timeout(
+            Duration::from_millis(50),
+            limiter.acquire("http://127.0.0.1:2/"),
+        );
+        assert!(waiting.await.is_err());
+
+        drop(held);
+        time::timeout(
+            Duration::from_secs(1),
+            limiter.acquire("http://127.0.0.1:2/"),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_saturated_ip_does_not_hold_up_other_hosts() {
+        let limiter = Arc::new(Limiter::new(HostLimits {
+            per_host: Some(1),
+            per_ip: Some(1),
+            ..HostLimits::default()
+        }));
+        // Two hosts sharing one address, and one elsewhere
+        limiter
+            .resolve_all([
+                "http://127.0.0.1/".to_string(),
+                "http://127.0.0.2/".to_string(),
+            ])
+            .await;
+        let shared = limiter.resolved_ip("http://127.0.0.1/");
+        limiter
+            .resolved
+            .lock()
+            .unwrap()
+            .insert("a.example".to_string(), shared);
+
+        let held = limiter.try_acquire("http://127.0.0.1/1").unwrap();
+        assert!(matches!(
+            limiter.try_acquire("http://a.example/"),
+            Err(None)
+        ));
+        assert!(limiter.try_acquire("http://127.0.0.2/").is_ok());
+
+        // The refused host kept its own slot, and gets the IP once released
+        let released = limiter.released();
+        tokio::pin!(released);
+        released.as_mut().enable();
+        drop(held);
+        time::timeout(Duration::from_secs(1), released)
+            .await
+            .unwrap();
+        assert!(limiter.try_acquire("http://a.example/").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_waiting_for_an_ip_holds_no_host_slot() {
+        let limiter = Arc::new(Limiter::new(HostLimits {
+            per_host: Some(1),
+            per_ip: Some(1),
+            ..HostLimits::default()
+        }));
+        let held = limiter.acquire("http://127.0.0.1/").await;
+        let shared = limiter.resolved_ip("http://127.0.0.1/");
+        limiter
+            .resolved
+            .lock()
+            .unwrap()
+            .insert("a.example".to_string(), shared);
+
+        let waiting = tokio::spawn({
+            let limiter = Arc::clone(&limiter);
+            async move {
+                limiter.acquire("http://a.example/").await;
+            }
+        });
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+        drop(limiter.try_acquire_host("http://a.example/").unwrap());
+
+        drop(held);
+        time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
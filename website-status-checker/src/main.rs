@@ -1,16 +1,29 @@
+use std::collections::HashMap;
 use std::env;
-use std::fs::{self, File};
-use std::io::Write;
-use std::sync::{mpsc, Arc, Mutex};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
 use std::thread;
-use std::time::{Duration, Instant};
-use reqwest::blocking::Client;
-
-struct WebsiteStatus {
-    url: String,
-    status: Result<u16, String>, // HTTP status code or error message
-    response_time_ms: u128,      // Response time in milliseconds
-    timestamp: String,           // Timestamp of the check
+use std::time::Duration;
+
+use website_status_checker::{
+    Alert, AlertEvent, AlertTracker, Checker, Config, Dashboard, EXIT_CONFIG_ERROR, EXIT_OK,
+    ExpectedStatus, FailThreshold, History, Metrics, OutputFormat, RedirectPolicy, ResponseTimes,
+    ResultWriter, RetryPolicy, RunReport, Shutdown, Target, Webhook, WebsiteStatus, parse_duration,
+};
+
+/// Set when results are written to stdout, so progress goes to stderr.
+static RESULTS_ON_STDOUT: AtomicBool = AtomicBool::new(false);
+
+/// Prints progress to stdout, or to stderr while stdout carries results.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if RESULTS_ON_STDOUT.load(Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
 }
 
 fn main() {
@@ -20,18 +33,50 @@ fn main() {
     // If no arguments are provided, print usage and exit
     if args.len() < 2 {
         print_usage();
-        std::process::exit(2);
+        std::process::exit(EXIT_CONFIG_ERROR);
     }
 
+    // Subcommands query the history database instead of checking anything
+    if let command @ ("history" | "uptime") = args[1].as_str() {
+        query_history(command, &args[2..]);
+    }
+
+    // `serve` watches the targets and serves a status page while it runs
+    let serve = args[1] == "serve";
+
     // Initialize default values
     let mut file_path: Option<String> = None;
-    let mut urls: Vec<String> = Vec::new();
-    let mut workers: usize = num_cpus::get(); // Default to number of logical CPU cores
-    let mut timeout: u64 = 5; // Default timeout in seconds
-    let mut retries: u32 = 0; // Default retries
+    let mut targets: Vec<Target> = Vec::new();
+    // Settings left unset fall back to the config file, then built-in defaults
+    let mut workers: Option<usize> = None; // Maximum requests in flight
+    let mut max_per_host: Option<usize> = None; // Requests in flight per host
+    let mut max_per_ip: Option<usize> = None; // Requests in flight per IP address
+    let mut host_rate: Option<f64> = None; // Requests per second per host
+    let mut timeout: Option<u64> = None; // Timeout in seconds
+    let mut retries: Option<u32> = None; // Retries after a failed attempt
+    let mut retry_delay: Option<Duration> = None; // Delay before the first retry
+    let mut retry_on: Option<Vec<u16>> = None; // Status codes that are retried
+    let mut max_body_bytes: Option<usize> = None; // Body bytes read for assertions
+    let mut cert_warning_days: Option<u32> = None; // Warn when a certificate expires this soon
+    let mut expected_status: Option<ExpectedStatus> = None; // Healthy status codes
+    let mut redirects: Option<RedirectPolicy> = None; // How redirects are followed
+    let mut watch = false; // Re-check targets until interrupted
+    let mut interval: Option<Duration> = None; // Default watch interval
+    let mut metrics_addr: Option<String> = None; // Where to serve /metrics
+    let mut listen_addr: Option<String> = None; // Where `serve` serves the dashboard
+    let mut fail_threshold: Option<FailThreshold> = None; // Share of targets allowed down
+    let mut db_path: Option<String> = None; // SQLite database to append results to
+    let mut report_path: Option<String> = None; // Where to write the HTML report
+    let mut junit_path: Option<String> = None; // Where to write JUnit XML
+    let mut junit_by_tag = false; // Group JUnit test suites by tag instead of input
+    let mut write_json = true; // Whether to write status.json
+    let mut format = OutputFormat::default(); // How results are written
+    let mut output_path: Option<String> = None; // Where results are written, `-` for stdout
+    let mut webhook_url: Option<String> = None; // Endpoint for up/down alerts
+    let mut alert_after: Option<u32> = None; // Consecutive failures before alerting
 
     // Parse arguments
-    let mut i = 1;
+    let mut i = if serve { 2 } else { 1 };
     while i < args.len() {
         match args[i].as_str() {
             "--file" => {
@@ -40,227 +85,880 @@ fn main() {
                     i += 1;
                 } else {
                     eprintln!("Error: --file requires a file path");
-                    std::process::exit(2);
+                    std::process::exit(EXIT_CONFIG_ERROR);
                 }
             }
             "--workers" => {
                 if i + 1 < args.len() {
-                    workers = args[i + 1].parse().unwrap_or_else(|_| {
+                    workers = Some(args[i + 1].parse().unwrap_or_else(|_| {
                         eprintln!("Error: --workers requires a valid number");
-                        std::process::exit(2);
-                    });
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
                     i += 1;
                 } else {
                     eprintln!("Error: --workers requires a value");
-                    std::process::exit(2);
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--max-per-host" => {
+                if i + 1 < args.len() {
+                    max_per_host = Some(args[i + 1].parse().unwrap_or_else(|_| {
+                        eprintln!("Error: --max-per-host requires a valid number");
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
+                    i += 1;
+                } else {
+                    eprintln!("Error: --max-per-host requires a value");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--max-per-ip" => {
+                if i + 1 < args.len() {
+                    max_per_ip = Some(args[i + 1].parse().unwrap_or_else(|_| {
+                        eprintln!("Error: --max-per-ip requires a valid number");
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
+                    i += 1;
+                } else {
+                    eprintln!("Error: --max-per-ip requires a value");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--host-rate" => {
+                if i + 1 < args.len() {
+                    let rate = args[i + 1]
+                        .parse()
+                        .ok()
+                        .filter(|rate: &f64| *rate > 0.0)
+                        .unwrap_or_else(|| {
+                            eprintln!("Error: --host-rate requires a positive number of requests per second");
+                            std::process::exit(EXIT_CONFIG_ERROR);
+                        });
+                    host_rate = Some(rate);
+                    i += 1;
+                } else {
+                    eprintln!("Error: --host-rate requires a value");
+                    std::process::exit(EXIT_CONFIG_ERROR);
                 }
             }
             "--timeout" => {
                 if i + 1 < args.len() {
-                    timeout = args[i + 1].parse().unwrap_or_else(|_| {
+                    timeout = Some(args[i + 1].parse().unwrap_or_else(|_| {
                         eprintln!("Error: --timeout requires a valid number");
-                        std::process::exit(2);
-                    });
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
                     i += 1;
                 } else {
                     eprintln!("Error: --timeout requires a value");
-                    std::process::exit(2);
+                    std::process::exit(EXIT_CONFIG_ERROR);
                 }
             }
             "--retries" => {
                 if i + 1 < args.len() {
-                    retries = args[i + 1].parse().unwrap_or_else(|_| {
+                    retries = Some(args[i + 1].parse().unwrap_or_else(|_| {
                         eprintln!("Error: --retries requires a valid number");
-                        std::process::exit(2);
-                    });
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
                     i += 1;
                 } else {
                     eprintln!("Error: --retries requires a value");
-                    std::process::exit(2);
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--retry-delay" => {
+                if i + 1 < args.len() {
+                    retry_delay = Some(parse_duration(&args[i + 1]).unwrap_or_else(|_| {
+                        eprintln!("Error: --retry-delay requires a valid duration such as 200ms");
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
+                    i += 1;
+                } else {
+                    eprintln!("Error: --retry-delay requires a value");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--retry-on" => {
+                if i + 1 < args.len() {
+                    let codes = args[i + 1]
+                        .split(',')
+                        .filter(|code| !code.trim().is_empty())
+                        .map(|code| code.trim().parse())
+                        .collect::<Result<Vec<u16>, _>>()
+                        .unwrap_or_else(|_| {
+                            eprintln!("Error: --retry-on requires status codes such as 429,503");
+                            std::process::exit(EXIT_CONFIG_ERROR);
+                        });
+                    retry_on = Some(codes);
+                    i += 1;
+                } else {
+                    eprintln!("Error: --retry-on requires a value");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--max-body-bytes" => {
+                if i + 1 < args.len() {
+                    max_body_bytes = Some(args[i + 1].parse().unwrap_or_else(|_| {
+                        eprintln!("Error: --max-body-bytes requires a valid number");
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
+                    i += 1;
+                } else {
+                    eprintln!("Error: --max-body-bytes requires a value");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--cert-warning-days" => {
+                if i + 1 < args.len() {
+                    cert_warning_days = Some(args[i + 1].parse().unwrap_or_else(|_| {
+                        eprintln!("Error: --cert-warning-days requires a valid number");
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
+                    i += 1;
+                } else {
+                    eprintln!("Error: --cert-warning-days requires a value");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--expect" => {
+                if i + 1 < args.len() {
+                    expected_status = Some(args[i + 1].parse().unwrap_or_else(|err| {
+                        eprintln!("Error: --expect: {}", err);
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
+                    i += 1;
+                } else {
+                    eprintln!("Error: --expect requires a value such as 2xx or 200-299,301");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--redirects" => {
+                if i + 1 < args.len() {
+                    redirects = Some(args[i + 1].parse().unwrap_or_else(|err| {
+                        eprintln!("Error: --redirects: {}", err);
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
+                    i += 1;
+                } else {
+                    eprintln!("Error: --redirects requires a value such as none, 5 or same-host");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--watch" => {
+                watch = true;
+            }
+            "--interval" => {
+                if i + 1 < args.len() {
+                    let value = parse_duration(&args[i + 1])
+                        .ok()
+                        .filter(|d| !d.is_zero())
+                        .unwrap_or_else(|| {
+                            eprintln!("Error: --interval requires a valid duration such as 30s");
+                            std::process::exit(EXIT_CONFIG_ERROR);
+                        });
+                    interval = Some(value);
+                    watch = true;
+                    i += 1;
+                } else {
+                    eprintln!("Error: --interval requires a value");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--metrics" => {
+                if i + 1 < args.len() {
+                    metrics_addr = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: --metrics requires an address such as 127.0.0.1:9898");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--listen" if serve => {
+                if i + 1 < args.len() {
+                    listen_addr = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: --listen requires an address such as 127.0.0.1:8080");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--db" => {
+                if i + 1 < args.len() {
+                    db_path = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: --db requires a file path");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--report" => {
+                if i + 1 < args.len() {
+                    report_path = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: --report requires a file path such as report.html");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--junit" => {
+                if i + 1 < args.len() {
+                    junit_path = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: --junit requires a file path such as junit.xml");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--junit-suites" => {
+                if i + 1 < args.len() {
+                    junit_by_tag = match args[i + 1].as_str() {
+                        "tag" => true,
+                        "file" => false,
+                        _ => {
+                            eprintln!("Error: --junit-suites must be 'tag' or 'file'");
+                            std::process::exit(EXIT_CONFIG_ERROR);
+                        }
+                    };
+                    i += 1;
+                } else {
+                    eprintln!("Error: --junit-suites requires 'tag' or 'file'");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--format" => {
+                if i + 1 < args.len() {
+                    format = args[i + 1].parse().unwrap_or_else(|err| {
+                        eprintln!("Error: --format: {}", err);
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    });
+                    i += 1;
+                } else {
+                    eprintln!("Error: --format requires json, csv or ndjson");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--output" => {
+                if i + 1 < args.len() {
+                    output_path = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: --output requires a file path, or - for stdout");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--no-json" => {
+                write_json = false;
+            }
+            "--webhook" => {
+                if i + 1 < args.len() {
+                    webhook_url = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: --webhook requires a URL");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--alert-after" => {
+                if i + 1 < args.len() {
+                    alert_after = Some(match args[i + 1].parse() {
+                        Ok(n @ 1..) => n,
+                        _ => {
+                            eprintln!("Error: --alert-after must be a positive number");
+                            std::process::exit(EXIT_CONFIG_ERROR);
+                        }
+                    });
+                    i += 1;
+                } else {
+                    eprintln!("Error: --alert-after requires a number");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            "--fail-threshold" => {
+                if i + 1 < args.len() {
+                    fail_threshold = Some(args[i + 1].parse().unwrap_or_else(|err| {
+                        eprintln!("Error: --fail-threshold: {}", err);
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    }));
+                    i += 1;
+                } else {
+                    eprintln!("Error: --fail-threshold requires a percentage such as 10%");
+                    std::process::exit(EXIT_CONFIG_ERROR);
                 }
             }
             _ => {
                 // Treat as a URL
-                urls.push(args[i].clone());
+                targets.push(Target::new(args[i].clone()));
             }
         }
         i += 1;
     }
 
-    // Read URLs from file if provided
-    if let Some(path) = file_path {
-        match fs::read_to_string(&path) {
-            Ok(contents) => {
-                for line in contents.lines() {
-                    let line = line.trim();
-                    if !line.is_empty() && !line.starts_with('#') {
-                        urls.push(line.to_string());
-                    }
-                }
-            }
-            Err(err) => {
-                eprintln!("Error reading file {}: {}", path, err);
-                std::process::exit(2);
-            }
-        }
-    }
+    // Read targets and defaults from file if provided
+    let config = match &file_path {
+        Some(path) => Config::load(path).unwrap_or_else(|err| {
+            eprintln!("Error: {}", err);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        }),
+        None => Config::default(),
+    };
+    targets.extend(config.targets.iter().cloned());
 
     // If no URLs are provided, print usage and exit
-    if urls.is_empty() {
+    if targets.is_empty() {
         eprintln!("Error: No URLs provided");
         print_usage();
-        std::process::exit(2);
-    }
-
-    // Create a channel for sending URLs to worker threads
-    let (tx, rx) = mpsc::channel::<String>();
-    let rx = Arc::new(Mutex::new(rx));
-
-    // Shared vector to collect results
-    let results = Arc::new(Mutex::new(Vec::new()));
-
-    // Spawn worker threads
-    let mut handles = Vec::new();
-    for _ in 0..workers {
-        let rx = Arc::clone(&rx);
-        let results = Arc::clone(&results);
-        let client = Client::new();
-        let handle = thread::spawn(move || {
-            while let Ok(url) = rx.lock().unwrap().recv() {
-                let start = Instant::now();
-                let result = check_website(&client, &url, timeout, retries);
-                let duration = start.elapsed();
-
-                let status = WebsiteStatus {
-                    url: url.clone(),
-                    status: result.map_err(|e| e.to_string()),
-                    response_time_ms: duration.as_millis(),
-                    timestamp: chrono::Local::now().to_rfc3339(),
-                };
-
-                // Live output to stdout
-                match &status.status {
-                    Ok(code) => println!(
-                        "[SUCCESS] {} - HTTP {} in {} ms at {}",
-                        status.url, code, status.response_time_ms, status.timestamp
-                    ),
-                    Err(err) => println!(
-                        "[FAILURE] {} - {} in {} ms at {}",
-                        status.url, err, status.response_time_ms, status.timestamp
-                    ),
-                }
-
-                // Add the result to the shared vector
-                results.lock().unwrap().push(status);
-            }
-        });
-        handles.push(handle);
+        std::process::exit(EXIT_CONFIG_ERROR);
     }
 
-    // Send URLs to the channel
-    for url in urls {
-        tx.send(url).expect("Failed to send URL to worker thread");
+    // Command-line flags override the config file's defaults
+    let mut builder = config.apply(Checker::builder());
+    if let Some(workers) = workers {
+        builder = builder.workers(workers);
+    }
+    if let Some(max_per_host) = max_per_host {
+        builder = builder.max_per_host(max_per_host);
+    }
+    if let Some(max_per_ip) = max_per_ip {
+        builder = builder.max_per_ip(max_per_ip);
+    }
+    if let Some(host_rate) = host_rate {
+        builder = builder.host_rate(host_rate);
+    }
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(Duration::from_secs(timeout));
+    }
+    if retry_delay.is_some() || retry_on.is_some() {
+        let mut retry = config
+            .retry
+            .clone()
+            .unwrap_or_else(|| RetryPolicy::new(config.retries.unwrap_or(0)));
+        if let Some(delay) = retry_delay {
+            retry = retry.with_base_delay(delay);
+        }
+        if let Some(codes) = retry_on {
+            retry = retry.with_retry_on(codes);
+        }
+        builder = builder.retry_policy(retry);
+    }
+    if let Some(retries) = retries {
+        builder = builder.retries(retries);
+    }
+    if let Some(interval) = interval {
+        builder = builder.interval(interval);
+    }
+    if let Some(max_body_bytes) = max_body_bytes {
+        builder = builder.max_body_bytes(max_body_bytes);
     }
+    if let Some(cert_warning_days) = cert_warning_days {
+        builder = builder.cert_warning_days(cert_warning_days);
+    }
+    if let Some(expected_status) = expected_status {
+        builder = builder.expected_status(expected_status);
+    }
+    if let Some(redirects) = redirects {
+        builder = builder.redirect_policy(redirects);
+    }
+    let checker = builder.build();
+    let output_path = output_path.unwrap_or_else(|| format.default_path().to_string());
+    RESULTS_ON_STDOUT.store(output_path == "-", Ordering::Relaxed);
+    let fail_threshold = fail_threshold.or(config.fail_threshold).unwrap_or_default();
+
+    // Serve Prometheus metrics for as long as checks are running
+    let metrics = Metrics::new();
+    let shutdown = Shutdown::new();
+    let metrics_server = metrics_addr.map(|addr| {
+        let server = metrics.serve(&addr, &shutdown).unwrap_or_else(|err| {
+            eprintln!("Error: cannot serve metrics on {}: {}", addr, err);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        });
+        say!("Serving metrics at http://{}/metrics", server.local_addr());
+        server
+    });
+
+    // Serve the status page and JSON API in serve mode
+    let dashboard = serve.then(|| Dashboard::new(&targets));
+    let dashboard_server = dashboard.as_ref().map(|dashboard| {
+        let addr = listen_addr.unwrap_or_else(|| "127.0.0.1:8080".to_string());
+        let server = dashboard.serve(&addr, &shutdown).unwrap_or_else(|err| {
+            eprintln!("Error: cannot serve the dashboard on {}: {}", addr, err);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        });
+        say!("Serving the dashboard at http://{}/", server.local_addr());
+        server
+    });
+
+    // Append every result to the history database, if one was given
+    let history = db_path.map(|path| {
+        History::open(&path).unwrap_or_else(|err| {
+            eprintln!("Error: {}", err);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        })
+    });
+
+    // Post state changes to the webhook, if one was given
+    let mut alerts = webhook_url.or(config.webhook.clone()).map(|url| {
+        let mut webhook = Webhook::new(url);
+        if let Some(retries) = config.webhook_retries {
+            webhook = webhook.with_retries(retries);
+        }
+        Alerts::start(webhook, alert_after.or(config.alert_after).unwrap_or(1))
+    });
+
+    // Stream CSV or NDJSON rows as each check completes
+    let mut stream = format.is_streaming().then(|| {
+        ResultWriter::create(format, &output_path).unwrap_or_else(|err| {
+            eprintln!("Error: cannot write {}: {}", output_path, err);
+            std::process::exit(EXIT_CONFIG_ERROR);
+        })
+    });
 
-    // Drop the sender to close the channel
-    drop(tx);
+    let mut sinks = Sinks {
+        metrics: &metrics,
+        dashboard: dashboard.as_ref(),
+        history: history.as_ref(),
+        alerts: alerts.as_mut(),
+        stream: stream.as_mut(),
+    };
+    let started_at = chrono::Local::now();
+    let results = if watch || serve {
+        watch_targets(&checker, targets, &shutdown, &mut sinks)
+    } else {
+        // Print each result as soon as its check finishes
+        let mut results = Vec::new();
+        for status in checker.spawn(targets) {
+            sinks.handle(&status);
+            results.push(status);
+        }
+        results
+    };
 
-    // Wait for all threads to finish
-    for handle in handles {
-        handle.join().expect("Failed to join worker thread");
+    shutdown.trigger();
+    if let Some(server) = metrics_server {
+        server.join();
+    }
+    if let Some(server) = dashboard_server {
+        server.join();
+    }
+    if let Some(alerts) = alerts {
+        alerts.finish();
     }
 
-    // Calculate summary statistics for successful responses
-    let results = results.lock().unwrap();
-    let mut times: Vec<u128> = results
-        .iter()
-        .filter_map(|s| if s.status.is_ok() { Some(s.response_time_ms) } else { None })
-        .collect();
+    // Count targets judged up and down
+    let up = results.iter().filter(|s| s.is_success()).count();
+    say!("\n{} up, {} down", up, results.len() - up);
+    say!("Limits: {}", describe_limits(&checker));
 
-    if !times.is_empty() {
-        times.sort();
-        let min = times.first().unwrap();
-        let max = times.last().unwrap();
-        let avg = times.iter().sum::<u128>() as f64 / times.len() as f64;
-        println!(
+    // Calculate summary statistics for successful responses
+    match ResponseTimes::of(&results) {
+        Some(times) => say!(
             "\nSummary statistics for successful responses:\n  Min: {} ms\n  Max: {} ms\n  Avg: {:.2} ms\n",
-            min, max, avg
-        );
-    } else {
-        println!("\nNo successful responses to summarize.\n");
+            times.min_ms,
+            times.max_ms,
+            times.avg_ms
+        ),
+        None => say!("\nNo successful responses to summarize.\n"),
     }
 
-    // Write results to a JSON file manually
-    let mut json = String::from("[\n");
-    for (i, status) in results.iter().enumerate() {
-        let status_str = match &status.status {
-            Ok(code) => format!("\"Ok\": {}", code),
-            Err(err) => format!("\"Err\": \"{}\"", err),
+    // Judge the run before the results are moved into the report
+    let verdict = fail_threshold.evaluate(&results);
+
+    // Write the run report as JSON, unless results were streamed
+    let report = RunReport::new(&checker, started_at, results);
+    match (format, output_path.as_str()) {
+        (OutputFormat::Json, _) if !write_json => {}
+        (OutputFormat::Json, "-") => report
+            .write_json_to(io::stdout())
+            .expect("Failed to write results to stdout"),
+        (OutputFormat::Json, path) => {
+            report
+                .write_json(path)
+                .unwrap_or_else(|err| panic!("Failed to write to {}: {}", path, err));
+            say!("Results written to {}", path);
+        }
+        (_, "-") => {}
+        (_, path) => say!("Results written to {}", path),
+    }
+
+    // Group test cases by the target's first tag, or by where it was listed
+    if let Some(path) = junit_path {
+        let suite_of = |status: &WebsiteStatus| match junit_by_tag {
+            true => status
+                .tags()
+                .first()
+                .cloned()
+                .unwrap_or_else(|| "untagged".to_string()),
+            false => status.source().unwrap_or("command line").to_string(),
         };
-        let entry = format!(
-            "  {{\n    \"url\": \"{}\",\n    \"status\": {{ {} }},\n    \"response_time_ms\": {},\n    \"timestamp\": \"{}\"\n  }}",
-            status.url, status_str, status.response_time_ms, status.timestamp
-        );
-        json.push_str(&entry);
-        if i < results.len() - 1 {
-            json.push_str(",\n");
+        match report.write_junit(&path, suite_of) {
+            Ok(()) => say!("JUnit results written to {}", path),
+            Err(err) => eprintln!("Error: cannot write JUnit results to {}: {}", path, err),
+        }
+    }
+
+    if let Some(path) = report_path {
+        match report.write_html(&path) {
+            Ok(()) => say!("Report written to {}", path),
+            Err(err) => eprintln!("Error: cannot write report to {}: {}", path, err),
+        }
+    }
+
+    if let Some(reason) = verdict.reason {
+        eprintln!("Run failed: {}", reason);
+    }
+    std::process::exit(verdict.exit_code);
+}
+
+/// Re-checks targets until SIGINT/SIGTERM, handling every result, and
+/// returns the latest result for each URL.
+fn watch_targets(
+    checker: &Checker,
+    targets: Vec<Target>,
+    shutdown: &Shutdown,
+    sinks: &mut Sinks,
+) -> Vec<WebsiteStatus> {
+    let handler = shutdown.clone();
+    ctrlc::set_handler(move || handler.trigger()).expect("Failed to install signal handler");
+
+    say!(
+        "Watching {} targets every {} s by default; press Ctrl-C to stop",
+        targets.len(),
+        checker.interval().as_secs_f64()
+    );
+
+    let mut latest: Vec<WebsiteStatus> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for status in checker.watch(targets, shutdown) {
+        sinks.handle(&status);
+        match positions.get(status.url()) {
+            Some(&position) => latest[position] = status,
+            None => {
+                positions.insert(status.url().to_string(), latest.len());
+                latest.push(status);
+            }
+        }
+    }
+    latest
+}
+
+/// Everything a result is handed to as soon as its check completes.
+struct Sinks<'a> {
+    metrics: &'a Metrics,
+    dashboard: Option<&'a Dashboard>,
+    history: Option<&'a History>,
+    alerts: Option<&'a mut Alerts>,
+    stream: Option<&'a mut ResultWriter>,
+}
+
+impl Sinks<'_> {
+    /// Prints a result, records it in the metrics, dashboard and history
+    /// database, streams it to the output file, and raises an alert if the
+    /// target went down or recovered.
+    fn handle(&mut self, status: &WebsiteStatus) {
+        print_status(status);
+        self.metrics.record(status);
+        if let Some(dashboard) = self.dashboard {
+            dashboard.record(status);
+        }
+        if let Some(history) = self.history
+            && let Err(err) = history.record(status)
+        {
+            eprintln!(
+                "Warning: cannot record {} in history: {}",
+                status.url(),
+                err
+            );
+        }
+        if let Some(stream) = self.stream.as_deref_mut()
+            && let Err(err) = stream.write(status)
+        {
+            eprintln!("Warning: cannot write {} to output: {}", status.url(), err);
+        }
+        if let Some(alerts) = self.alerts.as_deref_mut() {
+            alerts.observe(status);
         }
     }
-    json.push_str("\n]\n");
+}
+
+/// Tracks target state and delivers alerts on a background thread, so a
+/// slow or unreachable webhook does not hold up the checks.
+struct Alerts {
+    tracker: AlertTracker,
+    sender: mpsc::Sender<Alert>,
+    delivery: thread::JoinHandle<()>,
+}
 
-    let mut file = File::create("status.json").expect("Failed to create status.json");
-    file.write_all(json.as_bytes())
-        .expect("Failed to write to status.json");
+impl Alerts {
+    fn start(webhook: Webhook, failures_before_alert: u32) -> Alerts {
+        let (sender, receiver) = mpsc::channel::<Alert>();
+        let delivery = thread::spawn(move || {
+            for alert in receiver {
+                if let Err(err) = webhook.send(&alert) {
+                    eprintln!("Warning: cannot deliver alert for {}: {}", alert.url, err);
+                }
+            }
+        });
+        Alerts {
+            tracker: AlertTracker::new(failures_before_alert),
+            sender,
+            delivery,
+        }
+    }
+
+    fn observe(&mut self, status: &WebsiteStatus) {
+        if let Some(alert) = self.tracker.observe(status) {
+            match alert.event {
+                AlertEvent::Down => say!(
+                    "ALERT: {} is down after {} consecutive failure{}",
+                    alert.url,
+                    alert.consecutive_failures,
+                    if alert.consecutive_failures == 1 {
+                        ""
+                    } else {
+                        "s"
+                    }
+                ),
+                AlertEvent::Recovered => say!("ALERT: {} recovered", alert.url),
+            }
+            // The delivery thread only stops once the sender is dropped
+            let _ = self.sender.send(alert);
+        }
+    }
 
-    println!("Results written to status.json");
+    /// Waits for alerts still being delivered.
+    fn finish(self) {
+        drop(self.sender);
+        let _ = self.delivery.join();
+    }
 }
 
-fn check_website(client: &Client, url: &str, timeout: u64, retries: u32) -> Result<u16, String> {
-    let mut attempts = 0;
-
-    loop {
-        let response = client
-            .get(url)
-            .timeout(Duration::from_secs(timeout))
-            .send();
-
-        match response {
-            Ok(resp) => return Ok(resp.status().as_u16()),
-            Err(err) => {
-                attempts += 1;
-                if attempts > retries {
-                    return Err(err.to_string());
+/// Runs the `history` or `uptime` subcommand and exits.
+fn query_history(command: &str, args: &[String]) -> ! {
+    let mut url: Option<String> = None;
+    let mut db_path: Option<String> = None;
+    // How far back to look: 24 hours of history, 7 days of uptime
+    let mut window = match command {
+        "history" => Duration::from_secs(24 * 60 * 60),
+        _ => Duration::from_secs(7 * 24 * 60 * 60),
+    };
+    let window_flag = match command {
+        "history" => "--since",
+        _ => "--window",
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--db" => {
+                if i + 1 < args.len() {
+                    db_path = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: --db requires a file path");
+                    std::process::exit(EXIT_CONFIG_ERROR);
+                }
+            }
+            flag if flag == window_flag => {
+                if i + 1 < args.len() {
+                    window = parse_duration(&args[i + 1]).unwrap_or_else(|_| {
+                        eprintln!("Error: {} requires a valid duration such as 24h", flag);
+                        std::process::exit(EXIT_CONFIG_ERROR);
+                    });
+                    i += 1;
+                } else {
+                    eprintln!("Error: {} requires a value", flag);
+                    std::process::exit(EXIT_CONFIG_ERROR);
                 }
-                // Wait 100ms before retrying
-                thread::sleep(Duration::from_millis(100));
+            }
+            _ if url.is_none() => url = Some(args[i].clone()),
+            other => {
+                eprintln!("Error: unexpected argument '{}'", other);
+                std::process::exit(EXIT_CONFIG_ERROR);
             }
         }
+        i += 1;
     }
+
+    let (Some(url), Some(db_path)) = (url, db_path) else {
+        eprintln!("Error: {} requires a URL and --db", command);
+        print_usage();
+        std::process::exit(EXIT_CONFIG_ERROR);
+    };
+    let history = History::open(&db_path).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        std::process::exit(EXIT_CONFIG_ERROR);
+    });
+
+    let result = if command == "history" {
+        history.recent(&url, window).map(|results| {
+            for status in &results {
+                print_status(status);
+            }
+            println!(
+                "\n{} checks of {} in the last {}",
+                results.len(),
+                url,
+                describe_window(window)
+            );
+        })
+    } else {
+        history
+            .uptime(&url, window)
+            .map(|uptime| match uptime.percent() {
+                Some(percent) => {
+                    println!(
+                        "{}: {:.2}% up over the last {} ({} of {} checks succeeded)",
+                        url,
+                        percent,
+                        describe_window(window),
+                        uptime.succeeded,
+                        uptime.total
+                    );
+                    if let Some(avg) = uptime.average_response_time_ms {
+                        println!("  Avg response time: {:.2} ms", avg);
+                    }
+                    if let Some(last_failure) = uptime.last_failure {
+                        println!("  Last failure: {}", last_failure.to_rfc3339());
+                    }
+                }
+                None => println!("{}: no checks in the last {}", url, describe_window(window)),
+            })
+    };
+    if let Err(err) = result {
+        eprintln!("Error: {}: {}", db_path, err);
+        std::process::exit(EXIT_CONFIG_ERROR);
+    }
+    std::process::exit(EXIT_OK);
 }
 
-fn print_usage() {
-    println!("Usage: website_checker [--file sites.txt] [URL ...]");
-    println!("               [--workers N] [--timeout S] [--retries N]");
+/// Formats a query window with the largest unit that divides it, e.g. `7d`.
+fn describe_window(window: Duration) -> String {
+    let secs = window.as_secs();
+    match secs {
+        0 => format!("{}ms", window.as_millis()),
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+fn print_status(status: &WebsiteStatus) {
+    let timestamp = status.timestamp().to_rfc3339();
+    let target = match status.name() {
+        Some(name) => format!("{} ({})", name, status.url()),
+        None => status.url().to_string(),
+    };
+    let outcome = match (status.status_code(), status.failure()) {
+        (Some(code), None) => match status.warning() {
+            Some(warning) => format!(
+                "[WARNING] {} - HTTP {}{}, {}",
+                target,
+                code,
+                describe_redirects(status),
+                warning
+            ),
+            None => format!(
+                "[SUCCESS] {} - HTTP {}{}",
+                target,
+                code,
+                describe_redirects(status)
+            ),
+        },
+        (Some(code), Some(failure)) => format!(
+            "[FAILURE] {} - HTTP {}{}, {}",
+            target,
+            code,
+            describe_redirects(status),
+            failure
+        ),
+        // Checks without HTTP: DNS lookups show their answers
+        (None, None) => match status.answers() {
+            Some(answers) => format!("[SUCCESS] {} - {}", target, answers.join(", ")),
+            None => format!("[SUCCESS] {} - connected", target),
+        },
+        (None, Some(failure)) => format!("[FAILURE] {} - {}", target, failure),
+    };
+    say!(
+        "{} in {} ms at {} {}",
+        outcome,
+        status.response_time_ms(),
+        timestamp,
+        describe_phases(status)
+    );
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use reqwest::blocking::Client;
+/// Formats where redirects led, e.g. ` after 2 redirects to
+/// https://example.com/`, or nothing when none were followed.
+fn describe_redirects(status: &WebsiteStatus) -> String {
+    match status.redirects() {
+        0 => String::new(),
+        1 => format!(" after 1 redirect to {}", status.final_url()),
+        n => format!(" after {} redirects to {}", n, status.final_url()),
+    }
+}
 
-    #[test]
-    fn test_check_website_success() {
-        let client = Client::new();
-        let url = "https://www.rust-lang.org";
-        let result = check_website(&client, url, 5, 0);
-        assert!(result.is_ok());
+/// Formats the per-phase timings and attempts, e.g.
+/// `(dns 2 ms, connect 10 ms, ttfb 31 ms, download 4 ms; 1 attempt)` or
+/// `(connect 1 ms; 3 attempts: 503, 503, 200)`.
+fn describe_phases(status: &WebsiteStatus) -> String {
+    let phases: Vec<String> = status
+        .timings()
+        .phases()
+        .iter()
+        .map(|(name, ms)| format!("{} {} ms", name, ms))
+        .collect();
+    let attempts = match status.attempts() {
+        [] | [_] => "1 attempt".to_string(),
+        attempts => {
+            let outcomes: Vec<String> = attempts
+                .iter()
+                .map(|attempt| match (attempt.status_code, &attempt.error) {
+                    (Some(code), _) => code.to_string(),
+                    (None, Some(_)) => "error".to_string(),
+                    (None, None) => "connected".to_string(),
+                })
+                .collect();
+            format!("{} attempts: {}", attempts.len(), outcomes.join(", "))
+        }
+    };
+    if phases.is_empty() {
+        format!("({})", attempts)
+    } else {
+        format!("({}; {})", phases.join(", "), attempts)
     }
+}
 
-    #[test]
-    fn test_check_website_failure() {
-        let client = Client::new();
-        let url = "https://wikipedi@.org";
-        let result = check_website(&client, url, 5, 0);
-        assert!(result.is_err());
+/// Formats the concurrency and rate limits a run used, e.g.
+/// `8 in flight, 2 per host, 5 requests/s per host`.
+fn describe_limits(checker: &Checker) -> String {
+    let mut limits = vec![format!("{} in flight", checker.workers())];
+    if let Some(max_per_host) = checker.max_per_host() {
+        limits.push(format!("{} per host", max_per_host));
     }
-}
\ No newline at end of file
+    if let Some(max_per_ip) = checker.max_per_ip() {
+        limits.push(format!("{} per IP", max_per_ip));
+    }
+    if let Some(host_rate) = checker.host_rate() {
+        limits.push(format!("{} requests/s per host", host_rate));
+    }
+    limits.join(", ")
+}
+
+fn print_usage() {
+    println!("Usage: website_checker [--file sites.txt|sites.toml|sites.yaml] [URL ...]");
+    println!("               [--workers N] [--timeout S] [--retries N]");
+    println!("               [--max-per-host N] [--max-per-ip N] [--host-rate RPS]");
+    println!("               [--retry-delay DURATION] [--retry-on CODES]");
+    println!("               [--expect CODES] [--max-body-bytes N] [--cert-warning-days N]");
+    println!("               [--redirects none|N|same-host[,N]]");
+    println!("               [--watch] [--interval DURATION] [--metrics ADDR]");
+    println!("               [--fail-threshold PERCENT] [--db history.sqlite]");
+    println!("               [--webhook URL] [--alert-after N] [--report report.html]");
+    println!("               [--junit junit.xml] [--junit-suites file|tag] [--no-json]");
+    println!("               [--format json|csv|ndjson] [--output PATH|-]");
+    println!("       website_checker serve [--listen ADDR] [options as above]");
+    println!("       website_checker history URL --db history.sqlite [--since 24h]");
+    println!("       website_checker uptime URL --db history.sqlite [--window 7d]");
+    println!();
+    println!("Exit codes: 0 all up (or within --fail-threshold), 1 some down,");
+    println!("            2 invalid arguments or config, 3 all down");
+}
@@ -0,0 +1,381 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use tiny_http::{Header, Response, Server};
+
+use crate::shutdown::Shutdown;
+use crate::status::WebsiteStatus;
+
+/// Upper bounds, in seconds, of the response-time histogram buckets.
+pub(crate) const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Outcome labels of `website_checks_total`, in output order.
+const OUTCOMES: [&str; 6] = [
+    "success",
+    "request",
+    "unexpected_status",
+    "assertion",
+    "certificate",
+    "redirect",
+];
+
+/// Per-target metrics in the Prometheus text exposition format.
+///
+/// Cloning is cheap and every clone shares the same values, so one clone can
+/// be handed to [`Metrics::serve`] while another records results.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    targets: Arc<Mutex<BTreeMap<String, TargetMetrics>>>,
+}
+
+#[derive(Debug, Default)]
+struct TargetMetrics {
+    up: bool,
+    last_status_code: Option<u16>,
+    last_check: f64, // Unix timestamp in seconds
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+    outcomes: BTreeMap<&'static str, u64>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics::default()
+    }
+
+    /// Updates the metrics of the result's URL.
+    pub fn record(&self, status: &WebsiteStatus) {
+        let mut targets = self.targets.lock().unwrap();
+        let metrics = targets.entry(status.url().to_string()).or_default();
+
+        metrics.up = status.is_success();
+        metrics.last_status_code = status.status_code();
+        metrics.last_check = status.timestamp().timestamp_millis() as f64 / 1000.0;
+
+        let seconds = status.response_time().as_secs_f64();
+        for (bucket, bound) in metrics.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        metrics.sum += seconds;
+        metrics.count += 1;
+
+        let outcome = status.failure().map_or("success", |f| f.kind());
+        *metrics.outcomes.entry(outcome).or_default() += 1;
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let targets = self.targets.lock().unwrap();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "website_up",
+            "gauge",
+            "Whether the last check succeeded (1) or failed (0).",
+        );
+        for (url, m) in targets.iter() {
+            let _ = writeln!(out, "website_up{{url=\"{}\"}} {}", escape(url), m.up as u8);
+        }
+
+        header(
+            &mut out,
+            "website_last_status_code",
+            "gauge",
+            "HTTP status code of the last response, or 0 if none was received.",
+        );
+        for (url, m) in targets.iter() {
+            let code = m.last_status_code.unwrap_or(0);
+            let _ = writeln!(
+                out,
+                "website_last_status_code{{url=\"{}\"}} {}",
+                escape(url),
+                code
+            );
+        }
+
+        header(
+            &mut out,
+            "website_response_time_seconds",
+            "histogram",
+            "Total time spent on each check, including retries.",
+        );
+        for (url, m) in targets.iter() {
+            let url = escape(url);
+            for (bound, count) in BUCKETS.iter().zip(m.buckets) {
+                let _ = writeln!(
+                    out,
+                    "website_response_time_seconds_bucket{{url=\"{}\",le=\"{}\"}} {}",
+                    url, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "website_response_time_seconds_bucket{{url=\"{}\",le=\"+Inf\"}} {}",
+                url, m.count
+            );
+            let _ = writeln!(
+                out,
+                "website_response_time_seconds_sum{{url=\"{}\"}} {}",
+                url, m.sum
+            );
+            let _ = writeln!(
+                out,
+                "website_response_time_seconds_count{{url=\"{}\"}} {}",
+                url, m.count
+            );
+        }
+
+        header(
+            &mut out,
+            "website_checks_total",
+            "counter",
+            "Checks performed, by outcome.",
+        );
+        for (url, m) in targets.iter() {
+            for outcome in OUTCOMES {
+                let count = m.outcomes.get(outcome).copied().unwrap_or(0);
+                let _ = writeln!(
+                    out,
+                    "website_checks_total{{url=\"{}\",outcome=\"{}\"}} {}",
+                    escape(url),
+                    outcome,
+                    count
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "website_last_check_timestamp_seconds",
+            "gauge",
+            "Unix time at which the last check completed.",
+        );
+        for (url, m) in targets.iter() {
+            let _ = writeln!(
+                out,
+                "website_last_check_timestamp_seconds{{url=\"{}\"}} {}",
+                escape(url),
+                m.last_check
+            );
+        }
+
+        out
+    }
+
+    /// Serves the metrics at `/metrics` on `addr` from a background thread
+    /// until `shutdown` is triggered.
+    pub fn serve(
+        &self,
+        addr: impl ToSocketAddrs,
+        shutdown: &Shutdown,
+    ) -> io::Result<MetricsServer> {
+        let server = Server::http(addr).map_err(io::Error::other)?;
+        let local_addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| io::Error::other("metrics server is not listening on TCP"))?;
+
+        let metrics = self.clone();
+        let shutdown = shutdown.clone();
+        let handle = thread::spawn(move || {
+            while !shutdown.is_triggered() {
+                let request = match server.recv_timeout(Duration::from_millis(100)) {
+                    Ok(Some(request)) => request,
+                    Ok(None) => continue,
+                    Err(_) => break,
+                };
+                let response = if request.url() == "/metrics" {
+                    let content_type = Header::from_bytes(
+                        "Content-Type",
+                        "text/plain; version=0.0.4; charset=utf-8",
+                    )
+                    .unwrap();
+                    Response::from_string(metrics.render()).with_header(content_type)
+                } else {
+                    Response::from_string("Not Found").with_status_code(404)
+                };
+                let _ = request.respond(response);
+            }
+        });
+
+        Ok(MetricsServer { local_addr, handle })
+    }
+}
+
+/// A running metrics endpoint started by [`Metrics::serve`].
+pub struct MetricsServer {
+    local_addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Waits for the server thread to exit after shutdown is triggered.
+    pub fn join(self) {
+        self.handle
+            .join()
+            .expect("Failed to join metrics server thread");
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escapes a label value as required by the exposition format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::Failure;
+    use crate::target::Target;
+    use chrono::{Local, TimeZone};
+
+    fn status(url: &str, code: Option<u16>, failure: Option<Failure>, ms: u64) -> WebsiteStatus {
+        WebsiteStatus::new(
+            &Target::new(url),
+            code,
+            failure,
+            Duration::from_millis(ms),
+            Local.timestamp_opt(1_700_000_000, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_render_tracks_latest_state_and_counts() {
+        let metrics = Metrics::new();
+        metrics.record(&status("https://a.example", Some(200), None, 20));
+        metrics.record(&status(
+            "https://a.example",
+            Some(503),
+            Some(Failure::UnexpectedStatus("expected status 2xx".into())),
+            300,
+        ));
+        metrics.record(&status(
+            "https://b.example",
+            None,
+            Some(Failure::Certificate("certificate expired".into())),
+            40,
+        ));
+        metrics.record(&status(
+            "https://b.example",
+            Some(301),
+            Some(Failure::Redirect("more than 10 redirects".into())),
+            60,
+        ));
+
+        let text = metrics.render();
+        assert!(text.contains("website_up{url=\"https://a.example\"} 0\n"));
+        assert!(text.contains("website_last_status_code{url=\"https://a.example\"} 503\n"));
+        assert!(
+            text.contains(
+                "website_checks_total{url=\"https://a.example\",outcome=\"success\"} 1\n"
+            )
+        );
+        assert!(text.contains(
+            "website_checks_total{url=\"https://a.example\",outcome=\"unexpected_status\"} 1\n"
+        ));
+        assert!(text.contains(
+            "website_checks_total{url=\"https://b.example\",outcome=\"certificate\"} 1\n"
+        ));
+        assert!(
+            text.contains(
+                "website_checks_total{url=\"https://b.example\",outcome=\"redirect\"} 1\n"
+            )
+        );
+        assert!(text.contains(
+            "website_response_time_seconds_bucket{url=\"https://a.example\",le=\"0.025\"} 1\n"
+        ));
+        assert!(text.contains(
+            "website_response_time_seconds_bucket{url=\"https://a.example\",le=\"0.5\"} 2\n"
+        ));
+        assert!(
+            text.contains("website_response_time_seconds_count{url=\"https://a.example\"} 2\n")
+        );
+        assert!(text.contains(
+            "website_last_check_timestamp_seconds{url=\"https://a.example\"} 1700000000\n"
+        ));
+        assert!(text.contains("# TYPE website_response_time_seconds histogram\n"));
+    }
+
+    #[test]
+    fn test_every_failure_kind_is_an_outcome() {
+        let failures = [
+            Failure::Request(String::new()),
+            Failure::UnexpectedStatus(String::new()),
+            Failure::Assertion(String::new()),
+            Failure::Certificate(String::new()),
+            Failure::Redirect(String::new()),
+        ];
+        for failure in &failures {
+            // A new kind breaks this match until it is listed above
+            match failure {
+                Failure::Request(_)
+                | Failure::UnexpectedStatus(_)
+                | Failure::Assertion(_)
+                | Failure::Certificate(_)
+                | Failure::Redirect(_) => {}
+            }
+            assert!(OUTCOMES.contains(&failure.kind()), "{}", failure.kind());
+        }
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    #[test]
+    fn test_serve_exposes_metrics_endpoint() {
+        let metrics = Metrics::new();
+        metrics.record(&status(
+            "https://down.example",
+            None,
+            Some(Failure::Request("connection refused".into())),
+            5,
+        ));
+        let shutdown = Shutdown::new();
+        let server = metrics.serve("127.0.0.1:0", &shutdown).unwrap();
+        let base = format!("http://{}", server.local_addr());
+
+        let response = reqwest::blocking::get(format!("{}/metrics", base)).unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(
+            response.headers()["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("text/plain")
+        );
+        let body = response.text().unwrap();
+        assert!(body.contains("website_up{url=\"https://down.example\"} 0\n"));
+        assert!(body.contains("website_last_status_code{url=\"https://down.example\"} 0\n"));
+
+        let missing = reqwest::blocking::get(format!("{}/other", base)).unwrap();
+        assert_eq!(missing.status(), 404);
+
+        shutdown.trigger();
+        server.join();
+    }
+}
@@ -0,0 +1,495 @@
+//! A scriptable HTTP server on an ephemeral local port, so tests never
+//! depend on the internet or on DNS.
+//!
+//! Each path is given a script of [`Reply`]s, played back one per request;
+//! the last reply repeats once the script runs out. Unknown paths get
+//! `404 Not Found`.
+//!
+//! ```ignore
+//! let server = MockServer::start()
+//!     .route("/ok", [Reply::status(200)])
+//!     .route("/flaky", [Reply::status(503), Reply::status(503), Reply::status(200)]);
+//! ```
+//!
+//! [`MockServer::start_tls`] serves the same routes over HTTPS, with a
+//! certificate from [`self_signed`].
+//!
+//! [`MockDns`] is the DNS counterpart: a UDP server answering from a fixed
+//! table of records.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Local};
+use hickory_resolver::proto::op::{Message, ResponseCode};
+use hickory_resolver::proto::rr::rdata::{A, AAAA, CNAME, MX, TXT};
+use hickory_resolver::proto::rr::{Name, RData, Record};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::Notify;
+use tokio_native_tls::{TlsAcceptor, TlsStream};
+
+/// What the server does with one request.
+#[derive(Debug, Clone)]
+pub(crate) struct Reply {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    delay: Duration,       // Before the response head is sent
+    chunks: usize,         // Parts the body is sent in
+    chunk_delay: Duration, // Before each part of the body
+    reset: bool,           // Abort the connection instead of answering
+}
+
+impl Reply {
+    /// An empty response with `status`.
+    pub fn status(status: u16) -> Self {
+        Reply {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+            delay: Duration::ZERO,
+            chunks: 1,
+            chunk_delay: Duration::ZERO,
+            reset: false,
+        }
+    }
+
+    /// A redirect to `location`, which may be a path on this server.
+    pub fn redirect(status: u16, location: &str) -> Self {
+        Reply::status(status).header("Location", location)
+    }
+
+    /// Reads the request, then resets the connection without answering.
+    pub fn reset() -> Self {
+        Reply {
+            reset: true,
+            ..Reply::status(0)
+        }
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.body = body.as_bytes().to_vec();
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Waits `delay` before answering.
+    pub fn after(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Sends the body in `chunks` parts, pausing `pause` before each one.
+    pub fn slowly(mut self, chunks: usize, pause: Duration) -> Self {
+        self.chunks = chunks.max(1);
+        self.chunk_delay = pause;
+        self
+    }
+}
+
+/// A request as the server received it.
+#[derive(Debug, Clone)]
+pub(crate) struct Request {
+    pub method: String,
+    pub headers: Vec<(String, String)>, // Names lower-cased
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// The value of the first header called `name`.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Each path's script, shared with the server thread.
+type Routes = Arc<Mutex<HashMap<String, Route>>>;
+
+#[derive(Default)]
+struct Route {
+    script: Vec<Reply>,
+    hits: usize,
+    received: Vec<Request>,
+}
+
+/// A running mock server, stopped when dropped.
+pub(crate) struct MockServer {
+    base: String, // Scheme, host and port that URLs start with
+    routes: Routes,
+    shutdown: Arc<Notify>,
+}
+
+impl MockServer {
+    /// Starts a server with no routes on `127.0.0.1` and a free port.
+    pub fn start() -> Self {
+        let (addr, routes, shutdown) = MockServer::listen(None);
+        MockServer {
+            base: format!("http://{}", addr),
+            routes,
+            shutdown,
+        }
+    }
+
+    /// Starts an HTTPS server presenting the PEM certificate `cert`, whose
+    /// URLs use the host name `localhost`.
+    pub fn start_tls(cert: &str, key: &str) -> Self {
+        let identity = native_tls::Identity::from_pkcs8(cert.as_bytes(), key.as_bytes()).unwrap();
+        let acceptor = native_tls::TlsAcceptor::new(identity).unwrap();
+        let (addr, routes, shutdown) = MockServer::listen(Some(acceptor.into()));
+        MockServer {
+            base: format!("https://localhost:{}", addr.port()),
+            routes,
+            shutdown,
+        }
+    }
+
+    fn listen(tls: Option<TlsAcceptor>) -> (SocketAddr, Routes, Arc<Notify>) {
+        let routes = Arc::new(Mutex::new(HashMap::new()));
+        let shutdown = Arc::new(Notify::new());
+        let (addr_tx, addr_rx) = mpsc::channel();
+
+        let (served, stop) = (Arc::clone(&routes), Arc::clone(&shutdown));
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                addr_tx.send(listener.local_addr().unwrap()).unwrap();
+                let tls = tls.map(Arc::new);
+                loop {
+                    tokio::select! {
+                        _ = stop.notified() => break,
+                        accepted = listener.accept() => {
+                            let Ok((stream, _)) = accepted else {
+                                continue;
+                            };
+                            let routes = Arc::clone(&served);
+                            match tls.clone() {
+                                None => {
+                                    tokio::spawn(serve(stream, routes));
+                                }
+                                Some(tls) => {
+                                    tokio::spawn(async move {
+                                        // Clients that reject the certificate
+                                        // abort the handshake
+                                        if let Ok(stream) = tls.accept(stream).await {
+                                            serve(stream, routes).await;
+                                        }
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            });
+        });
+
+        (addr_rx.recv().unwrap(), routes, shutdown)
+    }
+
+    /// Answers requests for `path` with `script`, one reply per request.
+    pub fn route(self, path: &str, script: impl IntoIterator<Item = Reply>) -> Self {
+        let script: Vec<Reply> = script.into_iter().collect();
+        assert!(
+            !script.is_empty(),
+            "route {} needs at least one reply",
+            path
+        );
+        self.routes.lock().unwrap().insert(
+            path.to_string(),
+            Route {
+                script,
+                ..Route::default()
+            },
+        );
+        self
+    }
+
+    /// The full URL of `path` on this server.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    /// How many requests for `path` have been received.
+    pub fn hits(&self, path: &str) -> usize {
+        self.routes
+            .lock()
+            .unwrap()
+            .get(path)
+            .map_or(0, |route| route.hits)
+    }
+
+    /// Every request for `path` received so far, in order.
+    pub fn requests(&self, path: &str) -> Vec<Request> {
+        self.routes
+            .lock()
+            .unwrap()
+            .get(path)
+            .map_or_else(Vec::new, |route| route.received.clone())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown.notify_one();
+    }
+}
+
+/// A connection the server answers on, plain or TLS.
+trait Connection: AsyncRead + AsyncWrite + Unpin {
+    fn tcp(&self) -> &TcpStream;
+}
+
+impl Connection for TcpStream {
+    fn tcp(&self) -> &TcpStream {
+        self
+    }
+}
+
+impl Connection for TlsStream<TcpStream> {
+    fn tcp(&self) -> &TcpStream {
+        self.get_ref().get_ref().get_ref()
+    }
+}
+
+/// Answers the single request made on `stream`, then closes it.
+async fn serve(mut stream: impl Connection, routes: Routes) {
+    let Some((path, request)) = read_request(&mut stream).await else {
+        return;
+    };
+    let method = request.method.clone();
+
+    let reply = {
+        let mut routes = routes.lock().unwrap();
+        match routes.get_mut(&path) {
+            Some(route) => {
+                let reply = route.script[route.hits.min(route.script.len() - 1)].clone();
+                route.hits += 1;
+                route.received.push(request);
+                reply
+            }
+            None => Reply::status(404),
+        }
+    };
+
+    tokio::time::sleep(reply.delay).await;
+    if reply.reset {
+        // Closing with a zero linger sends RST instead of FIN
+        let _ = stream.tcp().set_linger(Some(Duration::ZERO));
+        return;
+    }
+
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\ncontent-length: {}\r\nconnection: close\r\n",
+        reply.status,
+        reply.body.len()
+    );
+    for (name, value) in &reply.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    if stream.write_all(head.as_bytes()).await.is_err() || method == "HEAD" {
+        return;
+    }
+
+    let chunk_size = reply.body.len().div_ceil(reply.chunks).max(1);
+    for chunk in reply.body.chunks(chunk_size) {
+        tokio::time::sleep(reply.chunk_delay).await;
+        if stream.write_all(chunk).await.is_err() {
+            return;
+        }
+    }
+    let _ = stream.shutdown().await;
+}
+
+/// Reads a request head and any `content-length` body, returning the path
+/// without its query string and the request.
+async fn read_request(stream: &mut impl Connection) -> Option<(String, Request)> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let end = loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        let n = stream.read(&mut chunk).await.ok().filter(|&n| n > 0)?;
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..end]).into_owned();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?;
+    let path = target.split('?').next().unwrap_or(target).to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+
+    // Read the whole body so closing the connection does not reset it
+    let length: usize = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = buf[end..].to_vec();
+    while body.len() < length {
+        let n = stream.read(&mut chunk).await.ok().filter(|&n| n > 0)?;
+        body.extend_from_slice(&chunk[..n]);
+    }
+    Some((
+        path,
+        Request {
+            method,
+            headers,
+            body,
+        },
+    ))
+}
+
+/// A DNS server on `127.0.0.1` and a free UDP port, stopped when dropped.
+///
+/// Names in the table get their records of the type asked for, possibly
+/// none; any other name gets `NXDOMAIN`.
+pub(crate) struct MockDns {
+    addr: SocketAddr,
+    records: Arc<Mutex<Vec<Record>>>,
+    shutdown: Arc<Notify>,
+}
+
+impl MockDns {
+    pub fn start() -> Self {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let shutdown = Arc::new(Notify::new());
+        let (addr_tx, addr_rx) = mpsc::channel();
+
+        let (served, stop) = (Arc::clone(&records), Arc::clone(&shutdown));
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+                addr_tx.send(socket.local_addr().unwrap()).unwrap();
+                let mut buf = [0u8; 4096];
+                loop {
+                    tokio::select! {
+                        _ = stop.notified() => break,
+                        received = socket.recv_from(&mut buf) => {
+                            let Ok((n, peer)) = received else {
+                                continue;
+                            };
+                            if let Some(reply) = answer(&buf[..n], &served) {
+                                let _ = socket.send_to(&reply, peer).await;
+                            }
+                        }
+                    }
+                }
+            });
+        });
+
+        MockDns {
+            addr: addr_rx.recv().unwrap(),
+            records,
+            shutdown,
+        }
+    }
+
+    /// Adds a record, written as it appears in answers: an address for `A`
+    /// and `AAAA`, a name for `CNAME`, `preference exchange` for `MX`, or
+    /// the text of a `TXT` record.
+    pub fn record(self, name: &str, record_type: &str, value: &str) -> Self {
+        let fqdn = |name: &str| Name::from_ascii(format!("{}.", name)).unwrap();
+        let data = match record_type {
+            "A" => RData::A(A(value.parse().unwrap())),
+            "AAAA" => RData::AAAA(AAAA(value.parse().unwrap())),
+            "CNAME" => RData::CNAME(CNAME(fqdn(value))),
+            "MX" => {
+                let (preference, exchange) = value.split_once(' ').unwrap();
+                RData::MX(MX::new(preference.parse().unwrap(), fqdn(exchange)))
+            }
+            "TXT" => RData::TXT(TXT::new(vec![value.to_string()])),
+            _ => panic!("unsupported record type {}", record_type),
+        };
+        self.records
+            .lock()
+            .unwrap()
+            .push(Record::from_rdata(fqdn(name), 60, data));
+        self
+    }
+
+    /// The address to send queries to.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for MockDns {
+    fn drop(&mut self) {
+        self.shutdown.notify_one();
+    }
+}
+
+/// The encoded response to the query in `request`.
+fn answer(request: &[u8], records: &Mutex<Vec<Record>>) -> Option<Vec<u8>> {
+    let request = Message::from_vec(request).ok()?;
+    let query = request.queries.first()?.clone();
+    let mut response = Message::response(request.metadata.id, request.metadata.op_code);
+    response.metadata.recursion_desired = request.metadata.recursion_desired;
+    response.metadata.recursion_available = true;
+    response.add_query(query.clone());
+
+    let records = records.lock().unwrap();
+    let named: Vec<&Record> = records
+        .iter()
+        .filter(|record| record.name.eq_case(query.name()) || record.name == *query.name())
+        .collect();
+    if named.is_empty() {
+        response.metadata.response_code = ResponseCode::NXDomain;
+    }
+    response.add_answers(
+        named
+            .into_iter()
+            .filter(|record| record.record_type() == query.query_type())
+            .cloned(),
+    );
+    response.to_vec().ok()
+}
+
+/// A self-signed certificate for `names`, valid from a day ago until the
+/// start of the day `not_after` falls on, as PEM `(certificate, key)`.
+pub(crate) fn self_signed(names: &[&str], not_after: DateTime<Local>) -> (String, String) {
+    let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    let mut params = rcgen::CertificateParams::new(names).unwrap();
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "mock server");
+    let yesterday = Local::now() - chrono::Duration::days(1);
+    params.not_before = rcgen::date_time_ymd(
+        yesterday.year(),
+        yesterday.month() as u8,
+        yesterday.day() as u8,
+    );
+    params.not_after = rcgen::date_time_ymd(
+        not_after.year(),
+        not_after.month() as u8,
+        not_after.day() as u8,
+    );
+    let key = rcgen::KeyPair::generate().unwrap();
+    let cert = params.self_signed(&key).unwrap();
+    (cert.pem(), key.serialize_pem())
+}
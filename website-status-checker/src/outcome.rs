@@ -0,0 +1,191 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::status::WebsiteStatus;
+
+/// Exit code when every target is up, or the failures are within the
+/// [`FailThreshold`].
+pub const EXIT_OK: i32 = 0;
+/// Exit code when some, but not all, targets are down.
+pub const EXIT_SOME_DOWN: i32 = 1;
+/// Exit code for invalid arguments or an unreadable config file.
+pub const EXIT_CONFIG_ERROR: i32 = 2;
+/// Exit code when every target is down.
+pub const EXIT_ALL_DOWN: i32 = 3;
+
+/// Targets with this tag fail the run whenever they are down, regardless of
+/// the threshold.
+pub const CRITICAL_TAG: &str = "critical";
+
+/// How many targets may be down before a run counts as failed.
+///
+/// Parsed from a percentage such as `10` or `10%`: the run fails if more
+/// than that share of targets is down, or if any target tagged
+/// [`critical`](CRITICAL_TAG) is down. The default of `0%` fails the run
+/// on any down target.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FailThreshold {
+    max_down_percent: f64,
+}
+
+/// Whether a run passed, and why not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    /// One of the `EXIT_*` codes.
+    pub exit_code: i32,
+    /// Why the run failed, or `None` if it passed.
+    pub reason: Option<String>,
+}
+
+impl FailThreshold {
+    pub fn new(max_down_percent: f64) -> Self {
+        FailThreshold {
+            max_down_percent: max_down_percent.clamp(0.0, 100.0),
+        }
+    }
+
+    pub fn max_down_percent(&self) -> f64 {
+        self.max_down_percent
+    }
+
+    /// Judges a run from its results.
+    pub fn evaluate(&self, results: &[WebsiteStatus]) -> Verdict {
+        let down: Vec<&WebsiteStatus> = results.iter().filter(|s| !s.is_success()).collect();
+        let critical: Vec<&str> = down
+            .iter()
+            .filter(|s| s.has_tag(CRITICAL_TAG))
+            .map(|s| s.label())
+            .collect();
+        let down_percent = if results.is_empty() {
+            0.0
+        } else {
+            down.len() as f64 * 100.0 / results.len() as f64
+        };
+
+        let reason = if !critical.is_empty() {
+            Some(format!("critical target down: {}", critical.join(", ")))
+        } else if down_percent > self.max_down_percent {
+            Some(format!(
+                "{} of {} targets down ({:.1}%), above the {} threshold",
+                down.len(),
+                results.len(),
+                down_percent,
+                self
+            ))
+        } else {
+            None
+        };
+
+        let exit_code = match reason {
+            None => EXIT_OK,
+            Some(_) if down.len() == results.len() => EXIT_ALL_DOWN,
+            Some(_) => EXIT_SOME_DOWN,
+        };
+        Verdict { exit_code, reason }
+    }
+}
+
+impl FromStr for FailThreshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s.trim().strip_suffix('%').unwrap_or(s.trim());
+        match number.trim().parse::<f64>() {
+            Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(FailThreshold::new(percent)),
+            _ => Err(format!(
+                "invalid threshold '{}': expected a percentage from 0 to 100",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FailThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.max_down_percent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::Failure;
+    use crate::target::Target;
+    use chrono::Local;
+    use std::time::Duration;
+
+    fn status(target: Target, up: bool) -> WebsiteStatus {
+        let failure = (!up).then(|| Failure::Request("refused".to_string()));
+        WebsiteStatus::new(&target, None, failure, Duration::ZERO, Local::now())
+    }
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!("10".parse(), Ok(FailThreshold::new(10.0)));
+        assert_eq!("12.5%".parse(), Ok(FailThreshold::new(12.5)));
+        assert!("150%".parse::<FailThreshold>().is_err());
+        assert!("some".parse::<FailThreshold>().is_err());
+    }
+
+    #[test]
+    fn test_default_fails_on_any_down_target() {
+        let threshold = FailThreshold::default();
+        let up = || status(Target::new("https://up.example"), true);
+        let down = || status(Target::new("https://down.example"), false);
+
+        assert_eq!(threshold.evaluate(&[up(), up()]).exit_code, EXIT_OK);
+        assert_eq!(
+            threshold.evaluate(&[up(), down()]).exit_code,
+            EXIT_SOME_DOWN
+        );
+        assert_eq!(
+            threshold.evaluate(&[down(), down()]).exit_code,
+            EXIT_ALL_DOWN
+        );
+        assert_eq!(threshold.evaluate(&[]).exit_code, EXIT_OK);
+    }
+
+    #[test]
+    fn test_threshold_tolerates_some_down_targets() {
+        let threshold: FailThreshold = "50%".parse().unwrap();
+        let mut results = vec![
+            status(Target::new("https://a.example"), true),
+            status(Target::new("https://b.example"), false),
+        ];
+        assert_eq!(
+            threshold.evaluate(&results),
+            Verdict {
+                exit_code: EXIT_OK,
+                reason: None
+            }
+        );
+
+        results.push(status(Target::new("https://c.example"), false));
+        let verdict = threshold.evaluate(&results);
+        assert_eq!(verdict.exit_code, EXIT_SOME_DOWN);
+        assert_eq!(
+            verdict.reason.as_deref(),
+            Some("2 of 3 targets down (66.7%), above the 50% threshold")
+        );
+    }
+
+    #[test]
+    fn test_critical_target_down_always_fails() {
+        let threshold = FailThreshold::new(100.0);
+        let results = [
+            status(Target::new("https://a.example"), true),
+            status(
+                Target::new("https://db.example")
+                    .with_name("Database")
+                    .with_tag(CRITICAL_TAG),
+                false,
+            ),
+        ];
+        let verdict = threshold.evaluate(&results);
+        assert_eq!(verdict.exit_code, EXIT_SOME_DOWN);
+        assert_eq!(
+            verdict.reason.as_deref(),
+            Some("critical target down: Database")
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::str::FromStr;
+
+use crate::status::WebsiteStatus;
+
+/// Columns of CSV output, one row per result.
+const CSV_COLUMNS: [&str; 17] = [
+    "timestamp",
+    "url",
+    "name",
+    "tags",
+    "success",
+    "status_code",
+    "failure_kind",
+    "failure_message",
+    "response_time_ms",
+    "dns_ms",
+    "connect_ms",
+    "tls_ms",
+    "ttfb_ms",
+    "download_ms",
+    "attempts",
+    "final_url",
+    "warning",
+];
+
+/// How a run's results are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The whole run as one JSON document, written once it finishes.
+    #[default]
+    Json,
+    /// A header, then one row per result as it completes.
+    Csv,
+    /// One JSON object per line per result as it completes.
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Where output goes when no path is given, e.g. `status.csv`.
+    pub fn default_path(&self) -> &'static str {
+        match self {
+            OutputFormat::Json => "status.json",
+            OutputFormat::Csv => "status.csv",
+            OutputFormat::Ndjson => "status.ndjson",
+        }
+    }
+
+    /// Whether results are written one at a time by a [`ResultWriter`].
+    pub fn is_streaming(&self) -> bool {
+        *self != OutputFormat::Json
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_ascii_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            _ => Err(format!(
+                "unknown format '{}' (expected json, csv or ndjson)",
+                format
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Ndjson => "ndjson",
+        })
+    }
+}
+
+/// Writes results as CSV rows or NDJSON lines, flushing each one as soon
+/// as it is written so a reader sees results live and a crash loses none.
+pub struct ResultWriter {
+    format: OutputFormat,
+    writer: Box<dyn Write + Send>,
+}
+
+impl ResultWriter {
+    /// Streams `format` to `writer`, starting with the CSV header.
+    ///
+    /// Fails for [`OutputFormat::Json`], which is written as a whole.
+    pub fn new(format: OutputFormat, writer: impl Write + Send + 'static) -> io::Result<Self> {
+        if !format.is_streaming() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "JSON output is written once the run finishes",
+            ));
+        }
+        let mut output = ResultWriter {
+            format,
+            writer: Box::new(writer),
+        };
+        if format == OutputFormat::Csv {
+            output.line(&CSV_COLUMNS.join(","))?;
+        }
+        Ok(output)
+    }
+
+    /// Streams `format` to the file at `path`, or to stdout for `-`.
+    pub fn create(format: OutputFormat, path: &str) -> io::Result<Self> {
+        match path {
+            "-" => ResultWriter::new(format, io::stdout()),
+            path => ResultWriter::new(format, BufWriter::new(File::create(path)?)),
+        }
+    }
+
+    /// Writes and flushes one result.
+    pub fn write(&mut self, status: &WebsiteStatus) -> io::Result<()> {
+        let line = match self.format {
+            OutputFormat::Csv => csv_row(status),
+            _ => serde_json::to_string(status)?,
+        };
+        self.line(&line)
+    }
+
+    fn line(&mut self, line: &str) -> io::Result<()> {
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+/// Formats `status` as a row of [`CSV_COLUMNS`].
+fn csv_row(status: &WebsiteStatus) -> String {
+    let number = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+    let timings = status.timings();
+    let fields = [
+        status.timestamp().to_rfc3339(),
+        status.url().to_string(),
+        status.name().unwrap_or_default().to_string(),
+        status.tags().join(";"),
+        status.is_success().to_string(),
+        number(status.status_code().map(u64::from)),
+        status
+            .failure()
+            .map(|f| f.kind())
+            .unwrap_or_default()
+            .to_string(),
+        status
+            .failure()
+            .map(|f| f.message())
+            .unwrap_or_default()
+            .to_string(),
+        status.response_time_ms().to_string(),
+        number(timings.dns_ms),
+        number(timings.connect_ms),
+        number(timings.tls_ms),
+        number(timings.ttfb_ms),
+        number(timings.download_ms),
+        status.attempts().len().to_string(),
+        status.final_url().to_string(),
+        status.warning().unwrap_or_default().to_string(),
+    ];
+    fields
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Quotes a field containing a comma, quote or line break, doubling quotes.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::Failure;
+    use crate::target::Target;
+    use chrono::{Local, TimeZone};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// A writer whose contents stay readable after it is handed over, and
+    /// which counts flushes.
+    #[derive(Clone, Default)]
+    struct Shared {
+        buffer: Arc<Mutex<Vec<u8>>>,
+        flushes: Arc<Mutex<usize>>,
+    }
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buffer.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            *self.flushes.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn text(&self) -> String {
+            String::from_utf8(self.buffer.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn statuses() -> [WebsiteStatus; 2] {
+        let timestamp = Local.timestamp_opt(1_700_000_000, 0).unwrap();
+        [
+            WebsiteStatus::new(
+                &Target::new("https://a.example")
+                    .with_name("A, Inc.")
+                    .with_tag("critical")
+                    .with_tag("api"),
+                Some(200),
+                None,
+                Duration::from_millis(42),
+                timestamp,
+            ),
+            WebsiteStatus::new(
+                &Target::new("https://b.example"),
+                None,
+                Some(Failure::Assertion("body contains \"Exception\"".into())),
+                Duration::from_millis(7),
+                timestamp,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_csv_rows_are_flushed_as_written() {
+        let shared = Shared::default();
+        let mut output = ResultWriter::new(OutputFormat::Csv, shared.clone()).unwrap();
+        let [up, down] = statuses();
+
+        output.write(&up).unwrap();
+        let flushed = *shared.flushes.lock().unwrap();
+        let text = shared.text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("timestamp,url,name,tags,success,status_code,"));
+        assert!(lines[1].contains(",https://a.example,\"A, Inc.\",critical;api,true,200,,,42,"));
+
+        output.write(&down).unwrap();
+        assert_eq!(*shared.flushes.lock().unwrap(), flushed + 1);
+        assert!(shared.text().lines().nth(2).unwrap().contains(
+            ",https://b.example,,,false,,assertion,\"body contains \"\"Exception\"\"\",7,"
+        ));
+    }
+
+    #[test]
+    fn test_ndjson_writes_one_result_per_line() {
+        let shared = Shared::default();
+        let mut output = ResultWriter::new(OutputFormat::Ndjson, shared.clone()).unwrap();
+        for status in statuses() {
+            output.write(&status).unwrap();
+        }
+
+        let text = shared.text();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["url"], "https://a.example");
+        assert_eq!(lines[1]["failure"]["kind"], "assertion");
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        assert_eq!("CSV".parse(), Ok(OutputFormat::Csv));
+        assert_eq!("ndjson".parse(), Ok(OutputFormat::Ndjson));
+        assert_eq!("json".parse(), Ok(OutputFormat::Json));
+        assert!("xml".parse::<OutputFormat>().is_err());
+        assert_eq!(OutputFormat::Ndjson.default_path(), "status.ndjson");
+        assert!(ResultWriter::new(OutputFormat::Json, Shared::default()).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Hops followed when a policy says `follow` without a number.
+pub const DEFAULT_MAX_HOPS: usize = 10;
+
+/// Whether, and how far, a check follows redirects.
+///
+/// Parsed from a comma-separated list: `none` to report the redirect
+/// itself, `follow` or a number of hops to follow, and `same-host` to fail
+/// on a redirect to another host, e.g. `same-host,3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedirectPolicy {
+    max_hops: usize, // Zero reports the first redirect as the response
+    same_host: bool, // Redirects to another host fail the check
+}
+
+impl RedirectPolicy {
+    /// Follows up to `max_hops` redirects; more fail the check.
+    pub fn follow(max_hops: usize) -> Self {
+        RedirectPolicy {
+            max_hops,
+            same_host: false,
+        }
+    }
+
+    /// Never follows redirects, so a `3xx` is judged like any response.
+    pub fn none() -> Self {
+        RedirectPolicy::follow(0)
+    }
+
+    /// Fails the check on a redirect to a different host.
+    pub fn with_same_host(mut self, same_host: bool) -> Self {
+        self.same_host = same_host;
+        self
+    }
+
+    pub fn max_hops(&self) -> usize {
+        self.max_hops
+    }
+
+    pub fn follows(&self) -> bool {
+        self.max_hops > 0
+    }
+
+    pub fn same_host(&self) -> bool {
+        self.same_host
+    }
+}
+
+impl Default for RedirectPolicy {
+    /// Follow up to ten redirects to any host, like most HTTP clients.
+    fn default() -> Self {
+        RedirectPolicy::follow(DEFAULT_MAX_HOPS)
+    }
+}
+
+impl FromStr for RedirectPolicy {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut policy = RedirectPolicy::default();
+        for item in spec.split(',').map(str::trim) {
+            match item {
+                "follow" => policy.max_hops = DEFAULT_MAX_HOPS,
+                "none" => policy.max_hops = 0,
+                "same-host" => policy.same_host = true,
+                _ => {
+                    policy.max_hops = item.parse().map_err(|_| {
+                        format!(
+                            "invalid redirect policy '{}' (expected none, follow, a number of hops or same-host)",
+                            item
+                        )
+                    })?;
+                }
+            }
+        }
+        Ok(policy)
+    }
+}
+
+impl fmt::Display for RedirectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.max_hops, self.same_host) {
+            (0, _) => f.write_str("none"),
+            (hops, false) => write!(f, "{}", hops),
+            (hops, true) => write!(f, "same-host,{}", hops),
+        }
+    }
+}
+
+/// One request of a check, in the order redirects were followed. The last
+/// hop is the response the check was judged on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hop {
+    pub url: String,
+    /// Status code of the response, or `None` if the request failed.
+    pub status_code: Option<u16>,
+    /// How long the request took to receive response headers.
+    pub duration_ms: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_policies() {
+        assert_eq!("follow".parse(), Ok(RedirectPolicy::default()));
+        assert_eq!("none".parse(), Ok(RedirectPolicy::none()));
+        assert_eq!("3".parse(), Ok(RedirectPolicy::follow(3)));
+        assert_eq!(
+            "same-host, 3".parse(),
+            Ok(RedirectPolicy::follow(3).with_same_host(true))
+        );
+        assert!(!RedirectPolicy::none().follows());
+        assert!("sometimes".parse::<RedirectPolicy>().is_err());
+        assert!("-1".parse::<RedirectPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for spec in ["none", "10", "same-host,2"] {
+            let policy: RedirectPolicy = spec.parse().unwrap();
+            assert_eq!(policy.to_string(), spec);
+        }
+    }
+}
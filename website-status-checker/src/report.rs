@@ -0,0 +1,371 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::checker::Checker;
+use crate::html_report;
+use crate::junit;
+use crate::status::WebsiteStatus;
+
+/// Version of the JSON report layout written by [`RunReport::write_json`].
+///
+/// Bumped whenever a field is removed, renamed or changes meaning; adding
+/// fields does not bump it.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// A complete run: metadata about how the checks were made plus every result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub schema_version: u32,
+    pub generator: String,
+    pub started_at: DateTime<Local>,
+    pub finished_at: DateTime<Local>,
+    pub settings: RunSettings,
+    pub summary: RunSummary,
+    pub results: Vec<WebsiteStatus>,
+}
+
+/// The checker configuration a run used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSettings {
+    pub workers: usize,
+    pub max_per_host: Option<usize>,
+    pub max_per_ip: Option<usize>,
+    pub host_rate: Option<f64>,
+    pub timeout_ms: u64,
+    pub retries: u32,
+    pub retry_delay_ms: u64,
+    pub retry_multiplier: f64,
+    pub retry_max_delay_ms: u64,
+    pub retry_jitter: f64,
+    pub retry_on: Vec<u16>,
+    pub respect_retry_after: bool,
+    pub max_body_bytes: usize,
+    pub expected_status: String,
+    #[serde(default)]
+    pub cert_warning_days: u32,
+    #[serde(default)]
+    pub redirects: String,
+}
+
+/// Counts of results by outcome.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+/// Minimum, maximum and mean response time of successful results.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResponseTimes {
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub avg_ms: f64,
+}
+
+impl ResponseTimes {
+    /// Summarizes the successful results, or `None` if there are none.
+    pub fn of(results: &[WebsiteStatus]) -> Option<Self> {
+        let times: Vec<u64> = results
+            .iter()
+            .filter(|s| s.is_success())
+            .map(WebsiteStatus::response_time_ms)
+            .collect();
+        Some(ResponseTimes {
+            min_ms: *times.iter().min()?,
+            max_ms: *times.iter().max()?,
+            avg_ms: times.iter().sum::<u64>() as f64 / times.len() as f64,
+        })
+    }
+}
+
+impl RunReport {
+    /// Builds a report for a run that started at `started_at` and has just
+    /// finished.
+    pub fn new(
+        checker: &Checker,
+        started_at: DateTime<Local>,
+        results: Vec<WebsiteStatus>,
+    ) -> Self {
+        let succeeded = results.iter().filter(|s| s.is_success()).count();
+        let retry = checker.retry_policy();
+        RunReport {
+            schema_version: SCHEMA_VERSION,
+            generator: concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")).to_string(),
+            started_at,
+            finished_at: Local::now(),
+            settings: RunSettings {
+                workers: checker.workers(),
+                max_per_host: checker.max_per_host(),
+                max_per_ip: checker.max_per_ip(),
+                host_rate: checker.host_rate(),
+                timeout_ms: duration_ms(checker.timeout()),
+                retries: checker.retries(),
+                retry_delay_ms: duration_ms(retry.base_delay()),
+                retry_multiplier: retry.multiplier(),
+                retry_max_delay_ms: duration_ms(retry.max_delay()),
+                retry_jitter: retry.jitter(),
+                retry_on: retry.retry_on().iter().copied().collect(),
+                respect_retry_after: retry.respects_retry_after(),
+                max_body_bytes: checker.max_body_bytes(),
+                expected_status: checker.expected_status().to_string(),
+                cert_warning_days: checker.cert_warning_days(),
+                redirects: checker.redirect_policy().to_string(),
+            },
+            summary: RunSummary {
+                total: results.len(),
+                succeeded,
+                failed: results.len() - succeeded,
+            },
+            results,
+        }
+    }
+
+    /// Writes the report as pretty-printed JSON.
+    pub fn write_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_json_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Writes the report as pretty-printed JSON to `writer`, such as stdout.
+    pub fn write_json_to(&self, mut writer: impl Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+
+    /// Writes the report as a standalone HTML page for sharing: a summary,
+    /// a latency histogram, failures grouped by kind and a sortable table of
+    /// every result. The page loads nothing from elsewhere.
+    pub fn write_html(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(html_report::render(self).as_bytes())?;
+        writer.flush()
+    }
+
+    /// Writes the report as JUnit XML for CI systems: each result is a
+    /// `<testcase>` in the `<testsuite>` named by `suite_of`, and failed
+    /// checks carry their failure message in `<failure>`.
+    pub fn write_junit(
+        &self,
+        path: impl AsRef<Path>,
+        suite_of: impl Fn(&WebsiteStatus) -> String,
+    ) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(junit::render(self, suite_of).as_bytes())?;
+        writer.flush()
+    }
+
+    /// Response time statistics of the run's successful results.
+    pub fn response_times(&self) -> Option<ResponseTimes> {
+        ResponseTimes::of(&self.results)
+    }
+
+    /// Reads a report previously written by [`RunReport::write_json`].
+    ///
+    /// Fails if the file was written with a newer, incompatible schema.
+    pub fn read_json(path: impl AsRef<Path>) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let report: RunReport = serde_json::from_reader(reader)?;
+        if report.schema_version > SCHEMA_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "unsupported schema version {} (expected at most {})",
+                    report.schema_version, SCHEMA_VERSION
+                ),
+            ));
+        }
+        Ok(report)
+    }
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockServer, Reply};
+    use crate::retry::Attempt;
+    use crate::retry::RetryPolicy;
+    use crate::status::Failure;
+    use crate::target::Target;
+    use crate::timing::Timings;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.json", name, std::process::id()))
+    }
+
+    fn sample_report() -> RunReport {
+        let results = vec![
+            WebsiteStatus::new(
+                &Target::new("https://example.com/?q=\"quoted\"\\path"),
+                Some(200),
+                None,
+                Duration::from_millis(42),
+                Local::now(),
+            ),
+            WebsiteStatus::new(
+                &Target::new("https://example.org"),
+                None,
+                Some(Failure::Request(
+                    "error: \"bad\" response\nwith a newline\tand tab".to_string(),
+                )),
+                Duration::from_millis(7),
+                Local::now(),
+            ),
+            WebsiteStatus::new(
+                &Target::new("https://example.net")
+                    .with_name("Example")
+                    .with_tag("critical"),
+                Some(200),
+                Some(Failure::Assertion(
+                    "body contains \"Exception\"".to_string(),
+                )),
+                Duration::from_millis(3),
+                Local::now(),
+            )
+            .with_timings(
+                Timings {
+                    dns_ms: Some(1),
+                    connect_ms: Some(2),
+                    tls_ms: Some(3),
+                    ttfb_ms: Some(4),
+                    download_ms: Some(5),
+                },
+                vec![
+                    Attempt::response(503, Duration::from_millis(4))
+                        .with_retry_delay(Duration::from_millis(100)),
+                    Attempt::response(200, Duration::from_millis(4)),
+                ],
+            ),
+        ];
+        RunReport::new(&Checker::default(), Local::now(), results)
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let report = sample_report();
+        let path = temp_path("report-round-trip");
+        report.write_json(&path).unwrap();
+        let read_back = RunReport::read_json(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read_back, report);
+        assert_eq!(read_back.summary.succeeded, 1);
+        assert_eq!(read_back.summary.failed, 2);
+    }
+
+    #[test]
+    fn test_report_escapes_special_characters() {
+        let json = serde_json::to_string(&sample_report()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        assert_eq!(
+            value["results"][0]["url"],
+            "https://example.com/?q=\"quoted\"\\path"
+        );
+        assert_eq!(value["results"][0]["status_code"], 200);
+        assert!(value["results"][0]["failure"].is_null());
+        assert!(value["results"][1]["status_code"].is_null());
+        assert_eq!(value["results"][1]["failure"]["kind"], "request");
+        assert_eq!(
+            value["results"][1]["failure"]["message"],
+            "error: \"bad\" response\nwith a newline\tand tab"
+        );
+        assert_eq!(value["results"][2]["failure"]["kind"], "assertion");
+        assert_eq!(value["results"][2]["attempts"][0]["status_code"], 503);
+        assert_eq!(value["results"][2]["attempts"][0]["retry_delay_ms"], 100);
+    }
+
+    #[test]
+    fn test_json_report_of_a_real_run() {
+        let server = MockServer::start()
+            .route("/ok", [Reply::status(200).body("healthy")])
+            .route("/flaky", [Reply::status(503), Reply::status(200)])
+            .route("/error", [Reply::status(500)])
+            .route("/reset", [Reply::reset()])
+            .route("/slow", [Reply::status(200).after(Duration::from_secs(2))]);
+        let checker = Checker::builder()
+            .workers(5)
+            .timeout(Duration::from_millis(300))
+            .retry_policy(RetryPolicy::new(1).with_base_delay(Duration::from_millis(10)))
+            .build();
+        let paths = ["/ok", "/flaky", "/error", "/reset", "/slow"];
+
+        let started_at = Local::now();
+        let results = checker.run(paths.iter().map(|path| server.url(path)));
+        let path = temp_path("report-real-run");
+        RunReport::new(&checker, started_at, results)
+            .write_json(&path)
+            .unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        assert_eq!(value["settings"]["timeout_ms"], 300);
+        assert_eq!(value["settings"]["retries"], 1);
+        assert_eq!(value["settings"]["redirects"], "10");
+        assert_eq!(value["summary"]["total"], 5);
+        assert_eq!(value["summary"]["succeeded"], 2);
+        assert_eq!(value["summary"]["failed"], 3);
+
+        // Results are in completion order, so look each one up by URL
+        let result = |path: &str| {
+            let url = server.url(path);
+            value["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|r| r["url"] == url.as_str())
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(result("/ok")["status_code"], 200);
+        assert!(result("/ok")["failure"].is_null());
+        assert_eq!(result("/ok")["redirect_chain"][0]["status_code"], 200);
+        assert_eq!(result("/flaky")["status_code"], 200);
+        assert_eq!(result("/flaky")["attempts"][0]["status_code"], 503);
+        assert_eq!(result("/flaky")["attempts"].as_array().unwrap().len(), 2);
+        assert_eq!(result("/error")["status_code"], 500);
+        assert_eq!(result("/error")["failure"]["kind"], "unexpected_status");
+        assert!(result("/reset")["status_code"].is_null());
+        assert_eq!(result("/reset")["failure"]["kind"], "request");
+        assert_eq!(result("/slow")["failure"]["kind"], "request");
+        assert!(result("/slow")["attempts"][1]["error"].is_string());
+    }
+
+    #[test]
+    fn test_response_times_cover_successes_only() {
+        let report = sample_report();
+        assert_eq!(
+            report.response_times(),
+            Some(ResponseTimes {
+                min_ms: 42,
+                max_ms: 42,
+                avg_ms: 42.0
+            })
+        );
+        assert_eq!(ResponseTimes::of(&report.results[1..]), None);
+    }
+
+    #[test]
+    fn test_read_rejects_newer_schema() {
+        let mut report = sample_report();
+        report.schema_version = SCHEMA_VERSION + 1;
+        let path = temp_path("report-newer-schema");
+        report.write_json(&path).unwrap();
+        let err = RunReport::read_json(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::timing::millis;
+
+/// When and how often a failed request is retried.
+///
+/// A request is retried after a transport error or a response whose status
+/// is in [`retry_on`](RetryPolicy::retry_on), up to
+/// [`retries`](RetryPolicy::retries) times. The n-th retry waits
+/// `base_delay * multiplier^(n-1)`, capped at `max_delay` and randomly
+/// spread by `jitter`. A `Retry-After` header on a retryable response
+/// replaces the computed delay, still capped at `max_delay`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    retries: u32,
+    base_delay: Duration,
+    multiplier: f64,
+    max_delay: Duration,
+    jitter: f64,
+    retry_on: BTreeSet<u16>,
+    respect_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    /// No retries; when enabled, 100 ms doubling up to 10 s with 10% jitter,
+    /// retrying on `429`, `502`, `503` and `504`.
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 0,
+            base_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(10),
+            jitter: 0.1,
+            retry_on: [429, 502, 503, 504].into_iter().collect(),
+            respect_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The default policy with `retries` retries.
+    pub fn new(retries: u32) -> Self {
+        RetryPolicy::default().with_retries(retries)
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Delay before the first retry.
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Factor the delay grows by after each retry. Values below one are
+    /// treated as one.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Upper bound on any single delay, including `Retry-After`.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Fraction by which each delay is randomly lengthened or shortened,
+    /// from `0.0` (exact delays) to `1.0`.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Status codes that are retried like transport errors.
+    pub fn with_retry_on(mut self, codes: impl IntoIterator<Item = u16>) -> Self {
+        self.retry_on = codes.into_iter().collect();
+        self
+    }
+
+    /// Whether a `Retry-After` header replaces the computed delay.
+    pub fn with_retry_after(mut self, respect: bool) -> Self {
+        self.respect_retry_after = respect;
+        self
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    pub fn base_delay(&self) -> Duration {
+        self.base_delay
+    }
+
+    pub fn multiplier(&self) -> f64 {
+        self.multiplier
+    }
+
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    pub fn retry_on(&self) -> &BTreeSet<u16> {
+        &self.retry_on
+    }
+
+    pub fn respects_retry_after(&self) -> bool {
+        self.respect_retry_after
+    }
+
+    /// Whether a response with status `code` should be retried.
+    pub fn should_retry_status(&self, code: u16) -> bool {
+        self.retry_on.contains(&code)
+    }
+
+    /// How long to wait before retry number `retry` (starting at 1), given
+    /// the `Retry-After` header of the failed attempt, if any.
+    pub fn delay(&self, retry: u32, retry_after: Option<&str>) -> Duration {
+        if self.respect_retry_after
+            && let Some(delay) = retry_after.and_then(parse_retry_after)
+        {
+            return delay.min(self.max_delay);
+        }
+
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.base_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        let spread = if self.jitter > 0.0 {
+            rand::thread_rng().gen_range(-self.jitter..=self.jitter)
+        } else {
+            0.0
+        };
+        let seconds = (backoff * (1.0 + spread)).min(self.max_delay.as_secs_f64());
+        Duration::from_secs_f64(seconds.max(0.0))
+    }
+}
+
+/// Parses a `Retry-After` value: either a number of seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&Utc) - Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// What happened on one attempt of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attempt {
+    /// Status code of the response, if one arrived.
+    pub status_code: Option<u16>,
+    /// Transport error, if no response arrived.
+    pub error: Option<String>,
+    /// How long the attempt took to receive response headers or fail.
+    pub duration_ms: u64,
+    /// How long the checker waited before the next attempt, if it retried.
+    pub retry_delay_ms: Option<u64>,
+}
+
+impl Attempt {
+    pub(crate) fn response(status_code: u16, duration: Duration) -> Self {
+        Attempt {
+            status_code: Some(status_code),
+            error: None,
+            duration_ms: millis(duration),
+            retry_delay_ms: None,
+        }
+    }
+
+    pub(crate) fn error(error: String, duration: Duration) -> Self {
+        Attempt {
+            status_code: None,
+            error: Some(error),
+            duration_ms: millis(duration),
+            retry_delay_ms: None,
+        }
+    }
+
+    /// An attempt that connected without an HTTP exchange, as for
+    /// `tcp://` targets.
+    pub(crate) fn connected(duration: Duration) -> Self {
+        Attempt {
+            status_code: None,
+            error: None,
+            duration_ms: millis(duration),
+            retry_delay_ms: None,
+        }
+    }
+
+    pub(crate) fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay_ms = Some(millis(delay));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_backoff_without_jitter() {
+        let policy = RetryPolicy::new(5)
+            .with_base_delay(Duration::from_millis(100))
+            .with_multiplier(2.0)
+            .with_max_delay(Duration::from_millis(500))
+            .with_jitter(0.0);
+        assert_eq!(policy.delay(1, None), Duration::from_millis(100));
+        assert_eq!(policy.delay(2, None), Duration::from_millis(200));
+        assert_eq!(policy.delay(3, None), Duration::from_millis(400));
+        assert_eq!(policy.delay(4, None), Duration::from_millis(500));
+        assert_eq!(policy.delay(40, None), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let policy = RetryPolicy::new(1)
+            .with_base_delay(Duration::from_millis(1000))
+            .with_jitter(0.25);
+        for _ in 0..100 {
+            let delay = policy.delay(1, None);
+            assert!(delay >= Duration::from_millis(750), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(1250), "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn test_retry_after_overrides_backoff() {
+        let policy = RetryPolicy::new(1).with_max_delay(Duration::from_secs(5));
+        assert_eq!(policy.delay(1, Some("2")), Duration::from_secs(2));
+        assert_eq!(policy.delay(1, Some("120")), Duration::from_secs(5));
+        assert_eq!(
+            policy.delay(1, Some("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Duration::ZERO
+        );
+
+        let ignoring = policy.clone().with_retry_after(false).with_jitter(0.0);
+        assert_eq!(ignoring.delay(1, Some("2")), Duration::from_millis(100));
+        // Unparseable values fall back to the computed backoff
+        assert!(policy.delay(1, Some("soon")) < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry_status(503));
+        assert!(policy.should_retry_status(429));
+        assert!(!policy.should_retry_status(500));
+
+        let custom = policy.with_retry_on([500]);
+        assert!(custom.should_retry_status(500));
+        assert!(!custom.should_retry_status(503));
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::certificate::CertificateInfo;
+use crate::redirect::Hop;
+use crate::retry::Attempt;
+use crate::target::Target;
+use crate::timing::Timings;
+
+/// Why a check failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum Failure {
+    /// No usable response was received (DNS, connection, timeout, ...).
+    Request(String),
+    /// A response was received but its status code was not an expected one.
+    UnexpectedStatus(String),
+    /// A response was received but a body assertion did not hold.
+    Assertion(String),
+    /// The TLS certificate was rejected: expired, not yet valid, or issued
+    /// for another host.
+    Certificate(String),
+    /// A redirect broke the target's redirect policy: too many hops, a
+    /// different host, or a loop.
+    Redirect(String),
+}
+
+impl Failure {
+    /// The failure's kind as written in the JSON report, e.g. `"request"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Failure::Request(_) => "request",
+            Failure::UnexpectedStatus(_) => "unexpected_status",
+            Failure::Assertion(_) => "assertion",
+            Failure::Certificate(_) => "certificate",
+            Failure::Redirect(_) => "redirect",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Failure::Request(message)
+            | Failure::UnexpectedStatus(message)
+            | Failure::Assertion(message)
+            | Failure::Certificate(message)
+            | Failure::Redirect(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+/// The outcome of checking a single website.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebsiteStatus {
+    url: String,
+    #[serde(default)]
+    name: Option<String>, // The target's label, if it has one
+    #[serde(default)]
+    tags: Vec<String>, // The target's tags
+    #[serde(skip)]
+    source: Option<String>, // Where the target was listed
+    status_code: Option<u16>, // HTTP status code, if a response arrived
+    failure: Option<Failure>, // Why the check failed, if it did
+    response_time_ms: u64,    // Response time in milliseconds
+    #[serde(default)]
+    timings: Timings, // Per-phase breakdown of the check
+    #[serde(default)]
+    attempts: Vec<Attempt>, // Every time the request was sent, in order
+    #[serde(default)]
+    redirect_chain: Vec<Hop>, // Every URL requested, following redirects
+    #[serde(default)]
+    certificate: Option<CertificateInfo>, // The final hop's leaf certificate
+    #[serde(default)]
+    warning: Option<String>, // A problem that did not fail the check
+    #[serde(default)]
+    answers: Option<Vec<String>>, // The records a DNS lookup returned
+    timestamp: DateTime<Local>, // Timestamp of the check
+}
+
+impl WebsiteStatus {
+    pub(crate) fn new(
+        target: &Target,
+        status_code: Option<u16>,
+        failure: Option<Failure>,
+        response_time: Duration,
+        timestamp: DateTime<Local>,
+    ) -> Self {
+        WebsiteStatus {
+            url: target.url().to_string(),
+            name: target.name().map(str::to_string),
+            tags: target.tags().to_vec(),
+            source: target.source().map(str::to_string),
+            status_code,
+            failure,
+            response_time_ms: response_time.as_millis() as u64,
+            timings: Timings::default(),
+            attempts: Vec::new(),
+            redirect_chain: Vec::new(),
+            certificate: None,
+            warning: None,
+            answers: None,
+            timestamp,
+        }
+    }
+
+    pub(crate) fn with_timings(mut self, timings: Timings, attempts: Vec<Attempt>) -> Self {
+        self.timings = timings;
+        self.attempts = attempts;
+        self
+    }
+
+    pub(crate) fn with_redirect_chain(mut self, redirect_chain: Vec<Hop>) -> Self {
+        self.redirect_chain = redirect_chain;
+        self
+    }
+
+    pub(crate) fn with_certificate(
+        mut self,
+        certificate: Option<CertificateInfo>,
+        warning: Option<String>,
+    ) -> Self {
+        self.certificate = certificate;
+        self.warning = warning;
+        self
+    }
+
+    pub(crate) fn with_answers(mut self, answers: Vec<String>) -> Self {
+        self.answers = Some(answers);
+        self
+    }
+
+    /// The URL that was checked.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The target's name, if it was given one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The target's name, falling back to its URL.
+    pub fn label(&self) -> &str {
+        self.name().unwrap_or(&self.url)
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Where the target was listed, such as the config file it was read
+    /// from; `None` for targets given in code or on the command line.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// The HTTP status code, or `None` if no response was received.
+    pub fn status_code(&self) -> Option<u16> {
+        self.status_code
+    }
+
+    /// Why the check failed, or `None` if it succeeded.
+    pub fn failure(&self) -> Option<&Failure> {
+        self.failure.as_ref()
+    }
+
+    /// Whether a response with an expected status code was received and
+    /// passed every assertion.
+    pub fn is_success(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// Total time spent on the check, including retries, in milliseconds.
+    pub fn response_time_ms(&self) -> u64 {
+        self.response_time_ms
+    }
+
+    pub fn response_time(&self) -> Duration {
+        Duration::from_millis(self.response_time_ms)
+    }
+
+    /// How long each phase of the check took.
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// The outcome of every time the request was sent, including retries,
+    /// in the order they happened.
+    pub fn attempts(&self) -> &[Attempt] {
+        &self.attempts
+    }
+
+    /// Each URL an HTTP check requested, starting with the target's and
+    /// following redirects; empty for other checks.
+    pub fn redirect_chain(&self) -> &[Hop] {
+        &self.redirect_chain
+    }
+
+    /// Where the redirects led: the URL of the response the check was
+    /// judged on.
+    pub fn final_url(&self) -> &str {
+        self.redirect_chain.last().map_or(&self.url, |hop| &hop.url)
+    }
+
+    /// How many redirects the check followed.
+    pub fn redirects(&self) -> usize {
+        self.redirect_chain.len().saturating_sub(1)
+    }
+
+    /// The certificate presented on the connection of the last hop, when
+    /// it was HTTPS and got as far as a TLS handshake. After redirects this
+    /// is the certificate the final response was served with.
+    pub fn certificate(&self) -> Option<&CertificateInfo> {
+        self.certificate.as_ref()
+    }
+
+    /// A problem worth attention that did not fail the check, such as a
+    /// certificate close to expiry.
+    pub fn warning(&self) -> Option<&str> {
+        self.warning.as_deref()
+    }
+
+    /// The records a `dns://` lookup returned, such as addresses or
+    /// `10 mx.example.com` for MX; `None` for other checks.
+    pub fn answers(&self) -> Option<&[String]> {
+        self.answers.as_deref()
+    }
+
+    /// When the check completed.
+    pub fn timestamp(&self) -> DateTime<Local> {
+        self.timestamp
+    }
+}
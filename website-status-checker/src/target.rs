@@ -0,0 +1,503 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use reqwest::Method;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::assertion::BodyAssertion;
+use crate::config::{expand_env, parse_method};
+use crate::duration::parse_duration;
+use crate::expected::ExpectedStatus;
+use crate::redirect::RedirectPolicy;
+
+/// A single website to check.
+///
+/// Settings left unset fall back to the [`Checker`](crate::Checker)'s
+/// defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    url: String,
+    name: Option<String>,                    // Human-friendly label for output
+    tags: Vec<String>,                       // Free-form labels for grouping
+    method: Option<Method>,                  // HTTP method to request with
+    headers: HeaderMap,                      // Extra request headers
+    body: Option<Vec<u8>>,                   // Request body to send
+    timeout: Option<Duration>,               // Per-request timeout
+    retries: Option<u32>,                    // Retries after a failed attempt
+    interval: Option<Duration>,              // How often to re-check in watch mode
+    assertions: Vec<BodyAssertion>,          // Conditions the response body must meet
+    expected_status: Option<ExpectedStatus>, // Codes that count as healthy
+    redirects: Option<RedirectPolicy>,       // Whether to follow redirects
+    final_url: Option<String>,               // Where redirects must end up
+    send: Option<String>,                    // Written after a TCP connect
+    expected_banner: Option<String>,         // Text a TCP server must reply with
+    expected_answers: Vec<String>,           // Records a DNS lookup must return
+    source: Option<String>,                  // Where the target was listed
+}
+
+impl Target {
+    pub fn new(url: impl Into<String>) -> Self {
+        Target {
+            url: url.into(),
+            name: None,
+            tags: Vec::new(),
+            method: None,
+            headers: HeaderMap::new(),
+            body: None,
+            timeout: None,
+            retries: None,
+            interval: None,
+            assertions: Vec::new(),
+            expected_status: None,
+            redirects: None,
+            final_url: None,
+            send: None,
+            expected_banner: None,
+            expected_answers: Vec::new(),
+            source: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn with_method(mut self, method: Method) -> Self {
+        self.method = Some(method);
+        self
+    }
+
+    /// Adds a request header, replacing any checker-wide header of the same
+    /// name.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Sends `body` with every request, e.g. JSON for a `POST` health
+    /// endpoint. Set a `Content-Type` header to describe it.
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// Re-check this target every `interval` in watch mode, overriding the
+    /// checker's default interval.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Adds a condition the response body must satisfy.
+    pub fn with_assertion(mut self, assertion: BodyAssertion) -> Self {
+        self.assertions.push(assertion);
+        self
+    }
+
+    /// Status codes that count as healthy for this target, overriding the
+    /// checker's default.
+    pub fn with_expected_status(mut self, expected_status: ExpectedStatus) -> Self {
+        self.expected_status = Some(expected_status);
+        self
+    }
+
+    /// How this target's redirects are followed, overriding the checker's
+    /// default.
+    pub fn with_redirects(mut self, redirects: RedirectPolicy) -> Self {
+        self.redirects = Some(redirects);
+        self
+    }
+
+    /// The URL the check must end up at after following redirects.
+    pub fn with_final_url(mut self, url: impl Into<String>) -> Self {
+        self.final_url = Some(url.into());
+        self
+    }
+
+    /// Text to write once a `tcp://` target's connection is open.
+    pub fn with_send(mut self, send: impl Into<String>) -> Self {
+        self.send = Some(send.into());
+        self
+    }
+
+    /// Text a `tcp://` target must send back, such as `SSH-2.0` or `+PONG`.
+    pub fn with_expected_banner(mut self, banner: impl Into<String>) -> Self {
+        self.expected_banner = Some(banner.into());
+        self
+    }
+
+    /// Adds a record a `dns://` target's lookup must return. Once any are
+    /// given, the lookup must return exactly this set of records.
+    pub fn with_expected_answer(mut self, answer: impl Into<String>) -> Self {
+        self.expected_answers.push(answer.into());
+        self
+    }
+
+    /// Records where the target was listed, such as the config file it was
+    /// read from, so results can be grouped by it.
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn method(&self) -> Option<&Method> {
+        self.method.as_ref()
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn body(&self) -> Option<&[u8]> {
+        self.body.as_deref()
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn retries(&self) -> Option<u32> {
+        self.retries
+    }
+
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    pub fn assertions(&self) -> &[BodyAssertion] {
+        &self.assertions
+    }
+
+    pub fn expected_status(&self) -> Option<&ExpectedStatus> {
+        self.expected_status.as_ref()
+    }
+
+    pub fn redirects(&self) -> Option<&RedirectPolicy> {
+        self.redirects.as_ref()
+    }
+
+    pub fn final_url(&self) -> Option<&str> {
+        self.final_url.as_deref()
+    }
+
+    pub fn send(&self) -> Option<&str> {
+        self.send.as_deref()
+    }
+
+    pub fn expected_banner(&self) -> Option<&str> {
+        self.expected_banner.as_deref()
+    }
+
+    pub fn expected_answers(&self) -> &[String] {
+        &self.expected_answers
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+}
+
+/// Parses one line of a target list: a URL optionally followed by options.
+/// Values containing spaces can be wrapped in double quotes, with `\"`
+/// standing for a literal quote. The value of `send` may also use `\r`,
+/// `\n`, `\t` and `\\`, and `header` and `body` values may refer to
+/// environment variables as `${NAME}`.
+///
+/// ```text
+/// https://api.example.com every 30s
+/// https://api.example.com/graphql method POST header "Authorization: Bearer ${API_TOKEN}" body "{}"
+/// https://example.com contains "Welcome" not-contains Exception matches "v\d+"
+/// https://example.com/old expect 301,302
+/// http://example.com redirects same-host,3 final-url https://example.com/
+/// tcp://cache.internal:6379 send "PING\r\n" expect-banner +PONG
+/// dns://example.com?type=MX expect-answers "10 mx1.example.com,20 mx2.example.com"
+/// ```
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(line)?;
+        let mut tokens = tokens.iter().map(String::as_str);
+        let url = tokens.next().ok_or("missing URL")?;
+        let mut target = Target::new(url);
+
+        while let Some(option) = tokens.next() {
+            let mut value = || {
+                tokens
+                    .next()
+                    .ok_or_else(|| format!("'{}' requires a value", option))
+            };
+            match option {
+                "method" => {
+                    target = target.with_method(parse_method(value()?)?);
+                }
+                "header" => {
+                    let header = value()?;
+                    let (name, value) = header
+                        .split_once(':')
+                        .ok_or_else(|| format!("header '{}' is not 'Name: value'", header))?;
+                    let name = HeaderName::from_bytes(name.trim().as_bytes())
+                        .map_err(|_| format!("invalid header name '{}'", name.trim()))?;
+                    let value = HeaderValue::from_str(&expand_env(value.trim())?)
+                        .map_err(|_| format!("invalid value for header '{}'", name))?;
+                    target = target.with_header(name, value);
+                }
+                "body" => {
+                    target = target.with_body(expand_env(value()?)?);
+                }
+                "contains" => {
+                    target = target.with_assertion(BodyAssertion::Contains(value()?.to_string()));
+                }
+                "not-contains" => {
+                    target =
+                        target.with_assertion(BodyAssertion::NotContains(value()?.to_string()));
+                }
+                "matches" => {
+                    target = target.with_assertion(BodyAssertion::matches(value()?)?);
+                }
+                "expect" => {
+                    target = target.with_expected_status(value()?.parse()?);
+                }
+                "redirects" => {
+                    target = target.with_redirects(value()?.parse()?);
+                }
+                "final-url" => {
+                    target = target.with_final_url(value()?);
+                }
+                "send" => {
+                    target = target.with_send(unescape(value()?)?);
+                }
+                "expect-banner" => {
+                    target = target.with_expected_banner(value()?);
+                }
+                "expect-answers" => {
+                    for answer in value()?.split(',') {
+                        target = target.with_expected_answer(answer.trim());
+                    }
+                }
+                "every" => {
+                    let interval = parse_duration(value()?)?;
+                    if interval.is_zero() {
+                        return Err("interval must be greater than zero".to_string());
+                    }
+                    target = target.with_interval(interval);
+                }
+                _ => return Err(format!("unrecognized option '{}'", option)),
+            }
+        }
+
+        Ok(target)
+    }
+}
+
+/// Splits a line on whitespace, keeping double-quoted sections together.
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        // Skip whitespace between tokens
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            return Ok(tokens);
+        };
+
+        let mut token = String::new();
+        if first == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') if chars.peek() == Some(&'"') => {
+                        token.push('"');
+                        chars.next();
+                    }
+                    Some(c) => token.push(c),
+                    None => return Err("unterminated quote".to_string()),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                token.push(c);
+            }
+        }
+        tokens.push(token);
+    }
+}
+
+/// Replaces the escapes `\r`, `\n`, `\t` and `\\` with the characters they
+/// stand for.
+fn unescape(value: &str) -> Result<String, String> {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('r') => unescaped.push('\r'),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => return Err(format!("unknown escape '\\{}'", other)),
+            None => return Err("trailing '\\'".to_string()),
+        }
+    }
+    Ok(unescaped)
+}
+
+impl From<String> for Target {
+    fn from(url: String) -> Self {
+        Target::new(url)
+    }
+}
+
+impl From<&str> for Target {
+    fn from(url: &str) -> Self {
+        Target::new(url)
+    }
+}
+
+impl From<&String> for Target {
+    fn from(url: &String) -> Self {
+        Target::new(url.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plain_url() {
+        let target: Target = "https://example.com".parse().unwrap();
+        assert_eq!(target, Target::new("https://example.com"));
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        let target: Target = "https://api.example.com every 30s".parse().unwrap();
+        assert_eq!(target.interval(), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_options() {
+        assert!("https://example.com every".parse::<Target>().is_err());
+        assert!("https://example.com every 0s".parse::<Target>().is_err());
+        assert!("https://example.com sometimes".parse::<Target>().is_err());
+        assert!("https://example.com matches (".parse::<Target>().is_err());
+        assert!(
+            "https://example.com contains \"open"
+                .parse::<Target>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_expected_status() {
+        let target: Target = "https://example.com/old expect 301,302 every 1m"
+            .parse()
+            .unwrap();
+        assert_eq!(target.expected_status(), Some(&"301,302".parse().unwrap()));
+        assert!("https://example.com expect 9xx".parse::<Target>().is_err());
+    }
+
+    #[test]
+    fn test_parse_assertions() {
+        let line = r#"https://example.com contains "Welcome home" not-contains Exception matches "v\d+ \"beta\"""#;
+        let target: Target = line.parse().unwrap();
+        assert_eq!(
+            target.assertions(),
+            &[
+                BodyAssertion::Contains("Welcome home".to_string()),
+                BodyAssertion::NotContains("Exception".to_string()),
+                BodyAssertion::matches(r#"v\d+ "beta""#).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_tcp_and_dns_options() {
+        let line = r#"tcp://cache.internal:6379 send "PING\r\n" expect-banner +PONG"#;
+        let target: Target = line.parse().unwrap();
+        assert_eq!(target.send(), Some("PING\r\n"));
+        assert_eq!(target.expected_banner(), Some("+PONG"));
+
+        let line =
+            r#"dns://example.com?type=MX expect-answers "10 mx1.example.com, 20 mx2.example.com""#;
+        let target: Target = line.parse().unwrap();
+        assert_eq!(
+            target.expected_answers(),
+            ["10 mx1.example.com", "20 mx2.example.com"]
+        );
+        assert!(r#"tcp://cache.internal:6379 send "PING\x""#.parse::<Target>().is_err());
+    }
+
+    #[test]
+    fn test_parse_request_options() {
+        let line = r#"https://example.com/api method post header "X-Api-Key: ${CARGO_PKG_NAME}" header "Host: internal" body "{\"ping\": true}""#;
+        let target: Target = line.parse().unwrap();
+        assert_eq!(target.method(), Some(&Method::POST));
+        assert_eq!(target.headers()["x-api-key"], env!("CARGO_PKG_NAME"));
+        assert_eq!(target.headers()["host"], "internal");
+        assert_eq!(target.body(), Some(&br#"{"ping": true}"#[..]));
+
+        assert!(
+            "https://example.com header NoColon"
+                .parse::<Target>()
+                .is_err()
+        );
+        assert!(
+            "https://example.com body ${STATUS_CHECKER_UNSET_VARIABLE}"
+                .parse::<Target>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_redirect_options() {
+        let target: Target =
+            "http://example.com redirects same-host,3 final-url https://example.com/"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            target.redirects(),
+            Some(&RedirectPolicy::follow(3).with_same_host(true))
+        );
+        assert_eq!(target.final_url(), Some("https://example.com/"));
+        assert!(
+            "http://example.com redirects maybe"
+                .parse::<Target>()
+                .is_err()
+        );
+    }
+}
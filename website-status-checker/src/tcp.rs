@@ -0,0 +1,262 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Instant;
+
+use reqwest::Url;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, lookup_host};
+use tokio::time;
+
+use crate::check::{BoxFuture, Check};
+use crate::http::CheckSettings;
+use crate::retry::Attempt;
+use crate::status::{Failure, WebsiteStatus};
+use crate::target::Target;
+use crate::timing::{Timings, millis};
+
+/// Most banner bytes read while waiting for the expected text.
+const MAX_BANNER_BYTES: usize = 64 * 1024;
+
+/// Checks `tcp://host:port` targets by opening a connection, optionally
+/// sending a string and waiting for an expected banner.
+pub(crate) struct TcpCheck {
+    settings: Arc<CheckSettings>,
+}
+
+impl TcpCheck {
+    pub fn new(settings: Arc<CheckSettings>) -> Self {
+        TcpCheck { settings }
+    }
+}
+
+impl Check for TcpCheck {
+    fn check<'a>(&'a self, target: &'a Target) -> BoxFuture<'a, WebsiteStatus> {
+        Box::pin(check_tcp(target, &self.settings))
+    }
+}
+
+async fn check_tcp(target: &Target, settings: &CheckSettings) -> WebsiteStatus {
+    let start = Instant::now();
+    let timeout = target.timeout().unwrap_or(settings.timeout);
+    let retry = match target.retries() {
+        Some(retries) => settings.retry.clone().with_retries(retries),
+        None => settings.retry.clone(),
+    };
+
+    let mut attempts = Vec::new();
+    let (timings, failure) = loop {
+        let attempt_start = Instant::now();
+        let mut timings = Timings::default();
+        let result = match time::timeout(timeout, probe(target, &mut timings)).await {
+            Ok(result) => result,
+            Err(_) => Err(Failure::Request(format!(
+                "timed out after {} ms",
+                millis(timeout)
+            ))),
+        };
+        let elapsed = attempt_start.elapsed();
+
+        // Only connection problems are worth another attempt; a server that
+        // answered with the wrong banner will answer the same way again
+        let attempt = match &result {
+            Ok(()) => Attempt::connected(elapsed),
+            Err(failure) => Attempt::error(failure.message().to_string(), elapsed),
+        };
+        let retryable = matches!(result, Err(Failure::Request(_)));
+        if !retryable || attempts.len() as u32 >= retry.retries() {
+            attempts.push(attempt);
+            break (timings, result.err());
+        }
+        let delay = retry.delay(attempts.len() as u32 + 1, None);
+        attempts.push(attempt.with_retry_delay(delay));
+        time::sleep(delay).await;
+    };
+
+    WebsiteStatus::new(target, None, failure, start.elapsed(), chrono::Local::now())
+        .with_timings(timings, attempts)
+}
+
+/// Resolves and connects to the target, then exchanges the optional send
+/// string and banner, recording each phase in `timings`.
+async fn probe(target: &Target, timings: &mut Timings) -> Result<(), Failure> {
+    let addr = resolve(target.url(), timings).await?;
+
+    let start = Instant::now();
+    let mut stream = TcpStream::connect(addr)
+        .await
+        .map_err(|err| Failure::Request(format!("connect to {} failed: {}", addr, err)))?;
+    timings.connect_ms = Some(millis(start.elapsed()));
+
+    let start = Instant::now();
+    if let Some(send) = target.send() {
+        stream
+            .write_all(send.as_bytes())
+            .await
+            .map_err(|err| Failure::Request(format!("send failed: {}", err)))?;
+    }
+    let Some(expected) = target.expected_banner() else {
+        return Ok(());
+    };
+
+    let mut banner = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let n = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|err| Failure::Request(format!("reading banner failed: {}", err)))?;
+        if timings.ttfb_ms.is_none() && n > 0 {
+            timings.ttfb_ms = Some(millis(start.elapsed()));
+        }
+        banner.extend_from_slice(&chunk[..n]);
+
+        let text = String::from_utf8_lossy(&banner);
+        if text.contains(expected) {
+            return Ok(());
+        }
+        if n == 0 || banner.len() >= MAX_BANNER_BYTES {
+            return Err(Failure::Assertion(format!(
+                "banner does not contain {:?} (got {:?})",
+                expected,
+                text.trim_end()
+            )));
+        }
+    }
+}
+
+/// The address of a `tcp://host:port` URL, timing the DNS lookup for host
+/// names.
+async fn resolve(url: &str, timings: &mut Timings) -> Result<SocketAddr, Failure> {
+    let invalid =
+        |reason: &str| Failure::Request(format!("invalid TCP target {}: {}", url, reason));
+    let parsed = Url::parse(url).map_err(|err| invalid(&err.to_string()))?;
+    let host = parsed.host().ok_or_else(|| invalid("missing host"))?;
+    let port = parsed
+        .port()
+        .ok_or_else(|| invalid("missing port, e.g. tcp://db.example.com:5432"))?;
+
+    // Hosts of non-special schemes such as tcp:// are never parsed as IPv4
+    let domain = match host {
+        url::Host::Ipv4(ip) => return Ok(SocketAddr::from((ip, port))),
+        url::Host::Ipv6(ip) => return Ok(SocketAddr::from((ip, port))),
+        url::Host::Domain(domain) => domain,
+    };
+    if let Ok(ip) = domain.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, port));
+    }
+
+    let start = Instant::now();
+    let addr = lookup_host((domain, port))
+        .await
+        .map_err(|err| Failure::Request(format!("cannot resolve {}: {}", domain, err)))?
+        .next()
+        .ok_or_else(|| Failure::Request(format!("no addresses for {}", domain)))?;
+    timings.dns_ms = Some(millis(start.elapsed()));
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Checker, RetryPolicy};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    fn check(target: Target) -> WebsiteStatus {
+        Checker::builder()
+            .timeout(Duration::from_secs(2))
+            .retry_policy(RetryPolicy::new(0).with_base_delay(Duration::ZERO))
+            .build()
+            .run([target])
+            .remove(0)
+    }
+
+    /// Accepts one connection, reads `expect` bytes, writes `reply`, and
+    /// returns the server's `tcp://` URL.
+    fn banner_server(expect: usize, reply: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![0u8; expect];
+            stream.read_exact(&mut request).unwrap();
+            stream.write_all(reply).unwrap();
+        });
+        url
+    }
+
+    #[test]
+    fn test_connect_records_latency() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+
+        let status = check(Target::new(url));
+        assert!(status.is_success(), "{:?}", status.failure());
+        assert_eq!(status.status_code(), None);
+        assert_eq!(status.timings().dns_ms, None);
+        assert!(status.timings().connect_ms.is_some());
+        let [attempt] = status.attempts() else {
+            panic!("expected one attempt: {:?}", status.attempts());
+        };
+        assert_eq!((attempt.status_code, &attempt.error), (None, &None));
+    }
+
+    #[test]
+    fn test_send_and_expect_banner() {
+        let url = banner_server(6, b"+PONG\r\n");
+        let target = Target::new(url)
+            .with_send("PING\r\n")
+            .with_expected_banner("+PONG");
+        let status = check(target);
+        assert!(status.is_success(), "{:?}", status.failure());
+        assert!(status.timings().ttfb_ms.is_some());
+
+        let url = banner_server(0, b"220 smtp.example.com ESMTP\r\n");
+        let status = check(Target::new(url).with_expected_banner("LMTP"));
+        assert_eq!(
+            status.failure(),
+            Some(&Failure::Assertion(
+                "banner does not contain \"LMTP\" (got \"220 smtp.example.com ESMTP\")".to_string()
+            ))
+        );
+        assert_eq!(status.attempts().len(), 1);
+    }
+
+    #[test]
+    fn test_connection_failures_are_retried() {
+        // Bind then drop to get a port with nothing listening on it
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let status = check(Target::new(format!("tcp://127.0.0.1:{}", port)).with_retries(1));
+        assert_eq!(status.failure().map(Failure::kind), Some("request"));
+        assert_eq!(status.attempts().len(), 2);
+
+        let status = check(Target::new("tcp://127.0.0.1"));
+        assert_eq!(
+            status.failure().map(Failure::message),
+            Some(
+                "invalid TCP target tcp://127.0.0.1: missing port, e.g. tcp://db.example.com:5432"
+            )
+        );
+    }
+
+    #[test]
+    fn test_silent_server_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let target = Target::new(url)
+            .with_expected_banner("hello")
+            .with_timeout(Duration::from_millis(100));
+
+        let status = check(target);
+        assert_eq!(
+            status.failure().map(Failure::message),
+            Some("timed out after 100 ms")
+        );
+    }
+}
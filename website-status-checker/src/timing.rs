@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How long each phase of a check took, in milliseconds.
+///
+/// For HTTP checks every phase is measured on the connection that carried
+/// the request producing the final response, after any redirects. DNS
+/// resolution, TCP connect and TLS handshake are only reported when that
+/// connection was opened for the request; one reused from the pool skips
+/// them. Time to first byte runs from when the connection was ready to
+/// when the response head arrived. A phase that did not happen (no DNS
+/// lookup for an IP address, no TLS for plain HTTP, or a phase after a
+/// failure) is `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timings {
+    pub dns_ms: Option<u64>,
+    pub connect_ms: Option<u64>,
+    pub tls_ms: Option<u64>,
+    pub ttfb_ms: Option<u64>,
+    pub download_ms: Option<u64>,
+}
+
+impl Timings {
+    /// The recorded phases as `(name, milliseconds)` pairs, in order.
+    pub fn phases(&self) -> Vec<(&'static str, u64)> {
+        [
+            ("dns", self.dns_ms),
+            ("connect", self.connect_ms),
+            ("tls", self.tls_ms),
+            ("ttfb", self.ttfb_ms),
+            ("download", self.download_ms),
+        ]
+        .into_iter()
+        .filter_map(|(name, ms)| ms.map(|ms| (name, ms)))
+        .collect()
+    }
+}
+
+pub(crate) fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases_skips_missing() {
+        let timings = Timings {
+            connect_ms: Some(1),
+            ttfb_ms: Some(5),
+            ..Timings::default()
+        };
+        assert_eq!(timings.phases(), vec![("connect", 1), ("ttfb", 5)]);
+    }
+}